@@ -0,0 +1,144 @@
+use crate::encoding::{Decode, DecodeError, Encode, EncodeError};
+use crate::key::ModMask;
+use crate::window::WindowId;
+
+/// Mouse button combined with modifiers, used to subscribe to button presses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Button {
+    /// Keyboard modifiers only, state of other buttons is not included.
+    pub modmask: ModMask,
+    pub index: ButtonIndex,
+}
+
+impl Button {
+    pub fn new(modmask: ModMask, index: ButtonIndex) -> Self {
+        Self { modmask, index }
+    }
+}
+
+impl From<(ModMask, ButtonIndex)> for Button {
+    fn from((modmask, index): (ModMask, ButtonIndex)) -> Self {
+        Self { modmask, index }
+    }
+}
+
+impl Decode for Button {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() < 3 {
+            return Err(DecodeError::BadFormat);
+        }
+
+        Ok(Self {
+            modmask: u16::decode(&buffer[0..])?.into(),
+            index: u8::decode(&buffer[2..])?.into(),
+        })
+    }
+}
+
+impl Encode for Button {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        if buffer.len() < self.encoded_size() {
+            return Err(EncodeError::BufferSize);
+        }
+
+        u16::from(self.modmask).encode_to(&mut buffer[0..])?;
+        u8::from(self.index).encode_to(&mut buffer[2..])
+    }
+
+    fn encoded_size(&self) -> usize {
+        3
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ButtonIndex(u8);
+
+#[allow(non_upper_case_globals)]
+impl ButtonIndex {
+    pub const Left: Self = Self(1);
+    pub const Middle: Self = Self(2);
+    pub const Right: Self = Self(3);
+    pub const ScrollUp: Self = Self(4);
+    pub const ScrollDown: Self = Self(5);
+    pub const ScrollLeft: Self = Self(6);
+    pub const ScrollRight: Self = Self(7);
+    pub const Back: Self = Self(8);
+    pub const Forward: Self = Self(9);
+}
+
+impl From<u8> for ButtonIndex {
+    fn from(index: u8) -> Self {
+        Self(index)
+    }
+}
+
+impl From<ButtonIndex> for u8 {
+    fn from(index: ButtonIndex) -> u8 {
+        index.0
+    }
+}
+
+/// Pointer position and state at the time of a button or motion event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Pointer {
+    pub root: WindowId,
+    /// Window the event is reported relative to.
+    pub window: WindowId,
+    /// Child of `window` that contains the pointer, if any.
+    pub child: Option<WindowId>,
+    pub root_x: i16,
+    pub root_y: i16,
+    pub x: i16,
+    pub y: i16,
+    /// Modifiers and buttons held before the event.
+    pub state: ModMask,
+}
+
+impl Decode for Pointer {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() < 22 {
+            return Err(DecodeError::BadFormat);
+        }
+
+        let child = WindowId::decode(&buffer[8..])?;
+        Ok(Self {
+            root: WindowId::decode(&buffer[0..])?,
+            window: WindowId::decode(&buffer[4..])?,
+            child: (child != 0).then_some(child),
+            root_x: i16::decode(&buffer[12..])?,
+            root_y: i16::decode(&buffer[14..])?,
+            x: i16::decode(&buffer[16..])?,
+            y: i16::decode(&buffer[18..])?,
+            state: u16::decode(&buffer[20..])?.into(),
+        })
+    }
+}
+
+impl Encode for Pointer {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        if buffer.len() < self.encoded_size() {
+            return Err(EncodeError::BufferSize);
+        }
+
+        self.root.encode_to(&mut buffer[0..])?;
+        self.window.encode_to(&mut buffer[4..])?;
+        self.child.unwrap_or(0).encode_to(&mut buffer[8..])?;
+        self.root_x.encode_to(&mut buffer[12..])?;
+        self.root_y.encode_to(&mut buffer[14..])?;
+        self.x.encode_to(&mut buffer[16..])?;
+        self.y.encode_to(&mut buffer[18..])?;
+        u16::from(self.state).encode_to(&mut buffer[20..])
+    }
+
+    fn encoded_size(&self) -> usize {
+        22
+    }
+}
@@ -1,6 +1,10 @@
+use crate::button::{Button, Pointer};
 use crate::encoding::{Decode, DecodeError, Encode, EncodeError};
-use crate::key::Key;
-use crate::window::{Window, WindowId};
+use crate::key::{Key, KeyBinding, KeyInput, Keysym, ModMask};
+use crate::monitor::MonitorList;
+#[cfg(feature = "std")]
+use crate::window::WindowType;
+use crate::window::{ClientRequest, ClientRequestKind, ScreenId, Window, WindowId, WorkspaceId};
 
 pub mod id {
     pub const KEY_PRESS: u32 = 1;
@@ -8,15 +12,97 @@ pub mod id {
     pub const WINDOW_ADD: u32 = 3;
     pub const WINDOW_REMOVE: u32 = 4;
     pub const WINDOW_CONFIGURE: u32 = 5;
+    pub const CLIENT_REQUEST: u32 = 6;
+    pub const WORKSPACE_CHANGE: u32 = 7;
+    pub const MONITORS_CHANGED: u32 = 8;
+    pub const WINDOW_TITLE_CHANGED: u32 = 9;
+    pub const WINDOW_URGENCY_CHANGED: u32 = 10;
+    pub const WINDOW_PROPERTY_CHANGED: u32 = 11;
+    pub const POINTER_ENTER: u32 = 12;
+    pub const POINTER_LEAVE: u32 = 13;
+    pub const WINDOW_FOCUS: u32 = 14;
+    pub const BUTTON_PRESS: u32 = 15;
+    pub const BUTTON_RELEASE: u32 = 16;
+    pub const POINTER_MOTION: u32 = 17;
+    pub const KEY_SYM_PRESS: u32 = 18;
+    pub const GRABBED_KEY_PRESS: u32 = 19;
+    pub const GRABBED_KEY_RELEASE: u32 = 20;
+    pub const KEYBOARD_GRAB_END: u32 = 21;
+    pub const WINDOW_CLICKED: u32 = 22;
+}
+
+/// Subscription flags, encoded in the upper 16 bits of the event id of a subscription. Event ids
+/// without flags are valid subscriptions as is.
+pub mod subscription_flag {
+    pub const MASK: u32 = 0xffff_0000;
+    /// Key press is grabbed synchronously and replayed to the focused client unless a plugin
+    /// consumes it.
+    pub const CONDITIONAL: u32 = 1 << 16;
+}
+
+pub mod filter_id {
+    pub const WINDOW: u32 = 1;
+    pub const CLASS: u32 = 2;
+    pub const INSTANCE: u32 = 3;
+    pub const TITLE: u32 = 4;
+    pub const WINDOW_TYPE: u32 = 5;
+    pub const MONITOR: u32 = 6;
+    pub const WORKSPACE: u32 = 7;
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Event {
     KeyPress(Key),
     KeyRelease(Key),
-    WindowAdd(WindowId),
-    WindowRemove(WindowId),
+    WindowAdd(WindowId, ScreenId),
+    WindowRemove(WindowId, ScreenId),
     WindowConfigure(Window),
+    ClientRequest(ClientRequest),
+    WorkspaceChange {
+        previous: WorkspaceId,
+        current: WorkspaceId,
+    },
+    MonitorsChanged {
+        screen: ScreenId,
+        monitors: MonitorList,
+    },
+    /// Effective window title (`_NET_WM_NAME` or `WM_NAME`) has changed.
+    WindowTitleChanged(WindowId),
+    /// Urgency flag of `WM_HINTS` has changed.
+    WindowUrgencyChanged {
+        window: WindowId,
+        urgent: bool,
+    },
+    /// Any property of a managed window has changed or was deleted.
+    WindowPropertyChanged {
+        window: WindowId,
+        atom: u32,
+    },
+    PointerEnter(WindowId),
+    PointerLeave(WindowId),
+    /// Input focus has moved to another client. `None` means that no managed window is focused.
+    WindowFocus {
+        previous: Option<WindowId>,
+        current: Option<WindowId>,
+    },
+    ButtonPress(Button, Pointer),
+    ButtonRelease(Button, Pointer),
+    /// Pointer motion while the pointer is grabbed by a button subscription.
+    PointerMotion(Pointer),
+    /// Key press of a bound keysym. Only sent for keysyms with active subscriptions.
+    KeySymPress(ModMask, Keysym),
+    /// Key press during a keyboard grab. Only sent to the plugin that holds the grab.
+    GrabbedKeyPress(KeyInput),
+    /// Key release during a keyboard grab. Only sent to the plugin that holds the grab.
+    GrabbedKeyRelease(KeyInput),
+    /// Keyboard grab was released by the WM with Escape or after inactivity timeout. Only sent
+    /// to the plugin that held the grab.
+    KeyboardGrabEnd,
+    /// Button press on an unfocused client with [Click](crate::window::FocusPolicy::Click)
+    /// focus policy. `Pointer::window` is the clicked client. The WM replays the click to the
+    /// client before plugins receive the event, so plugins can only disable the built-in
+    /// focusing with `focus_set_click_enabled`, not the replay.
+    WindowClicked(Button, Pointer),
 }
 
 impl Event {
@@ -26,9 +112,26 @@ impl Event {
         match self {
             KeyPress(_) => id::KEY_PRESS,
             KeyRelease(_) => id::KEY_RELEASE,
-            WindowAdd(_) => id::WINDOW_ADD,
-            WindowRemove(_) => id::WINDOW_REMOVE,
+            WindowAdd(..) => id::WINDOW_ADD,
+            WindowRemove(..) => id::WINDOW_REMOVE,
             WindowConfigure(_) => id::WINDOW_CONFIGURE,
+            ClientRequest(_) => id::CLIENT_REQUEST,
+            WorkspaceChange { .. } => id::WORKSPACE_CHANGE,
+            MonitorsChanged { .. } => id::MONITORS_CHANGED,
+            WindowTitleChanged(_) => id::WINDOW_TITLE_CHANGED,
+            WindowUrgencyChanged { .. } => id::WINDOW_URGENCY_CHANGED,
+            WindowPropertyChanged { .. } => id::WINDOW_PROPERTY_CHANGED,
+            PointerEnter(_) => id::POINTER_ENTER,
+            PointerLeave(_) => id::POINTER_LEAVE,
+            WindowFocus { .. } => id::WINDOW_FOCUS,
+            ButtonPress(..) => id::BUTTON_PRESS,
+            ButtonRelease(..) => id::BUTTON_RELEASE,
+            PointerMotion(_) => id::POINTER_MOTION,
+            KeySymPress(..) => id::KEY_SYM_PRESS,
+            GrabbedKeyPress(_) => id::GRABBED_KEY_PRESS,
+            GrabbedKeyRelease(_) => id::GRABBED_KEY_RELEASE,
+            KeyboardGrabEnd => id::KEYBOARD_GRAB_END,
+            WindowClicked(..) => id::WINDOW_CLICKED,
         }
     }
 }
@@ -48,9 +151,96 @@ impl Decode for Event {
         match id {
             id::KEY_PRESS => Key::decode(&buffer[4..]).map(Event::KeyPress),
             id::KEY_RELEASE => Key::decode(&buffer[4..]).map(Event::KeyRelease),
-            id::WINDOW_ADD => WindowId::decode(&buffer[4..]).map(Event::WindowAdd),
-            id::WINDOW_REMOVE => WindowId::decode(&buffer[4..]).map(Event::WindowRemove),
+            id::WINDOW_ADD | id::WINDOW_REMOVE => {
+                if buffer.len() < 12 {
+                    return Err(DecodeError::BadFormat);
+                }
+                let window = WindowId::decode(&buffer[4..])?;
+                let screen = ScreenId::decode(&buffer[8..])?;
+                if id == id::WINDOW_ADD {
+                    Ok(Event::WindowAdd(window, screen))
+                } else {
+                    Ok(Event::WindowRemove(window, screen))
+                }
+            }
             id::WINDOW_CONFIGURE => Window::decode(&buffer[4..]).map(Event::WindowConfigure),
+            id::CLIENT_REQUEST => ClientRequest::decode(&buffer[4..]).map(Event::ClientRequest),
+            id::WORKSPACE_CHANGE => {
+                if buffer.len() < 12 {
+                    return Err(DecodeError::BadFormat);
+                }
+                Ok(Event::WorkspaceChange {
+                    previous: WorkspaceId::decode(&buffer[4..])?,
+                    current: WorkspaceId::decode(&buffer[8..])?,
+                })
+            }
+            id::MONITORS_CHANGED => {
+                if buffer.len() < 8 {
+                    return Err(DecodeError::BadFormat);
+                }
+                Ok(Event::MonitorsChanged {
+                    screen: ScreenId::decode(&buffer[4..])?,
+                    monitors: MonitorList::decode(&buffer[8..])?,
+                })
+            }
+            id::WINDOW_TITLE_CHANGED => {
+                WindowId::decode(&buffer[4..]).map(Event::WindowTitleChanged)
+            }
+            id::WINDOW_URGENCY_CHANGED => {
+                if buffer.len() < 9 {
+                    return Err(DecodeError::BadFormat);
+                }
+                Ok(Event::WindowUrgencyChanged {
+                    window: WindowId::decode(&buffer[4..])?,
+                    urgent: buffer[8] != 0,
+                })
+            }
+            id::WINDOW_PROPERTY_CHANGED => {
+                if buffer.len() < 12 {
+                    return Err(DecodeError::BadFormat);
+                }
+                Ok(Event::WindowPropertyChanged {
+                    window: WindowId::decode(&buffer[4..])?,
+                    atom: u32::decode(&buffer[8..])?,
+                })
+            }
+            id::POINTER_ENTER => WindowId::decode(&buffer[4..]).map(Event::PointerEnter),
+            id::POINTER_LEAVE => WindowId::decode(&buffer[4..]).map(Event::PointerLeave),
+            id::WINDOW_FOCUS => {
+                if buffer.len() < 12 {
+                    return Err(DecodeError::BadFormat);
+                }
+                let window = |id: WindowId| (id != 0).then_some(id);
+                Ok(Event::WindowFocus {
+                    previous: window(WindowId::decode(&buffer[4..])?),
+                    current: window(WindowId::decode(&buffer[8..])?),
+                })
+            }
+            id::BUTTON_PRESS | id::BUTTON_RELEASE | id::WINDOW_CLICKED => {
+                if buffer.len() < 7 {
+                    return Err(DecodeError::BadFormat);
+                }
+                let button = Button::decode(&buffer[4..])?;
+                let pointer = Pointer::decode(&buffer[7..])?;
+                match id {
+                    id::BUTTON_PRESS => Ok(Event::ButtonPress(button, pointer)),
+                    id::BUTTON_RELEASE => Ok(Event::ButtonRelease(button, pointer)),
+                    _ => Ok(Event::WindowClicked(button, pointer)),
+                }
+            }
+            id::POINTER_MOTION => Pointer::decode(&buffer[4..]).map(Event::PointerMotion),
+            id::KEY_SYM_PRESS => {
+                if buffer.len() < 10 {
+                    return Err(DecodeError::BadFormat);
+                }
+                Ok(Event::KeySymPress(
+                    u16::decode(&buffer[4..])?.into(),
+                    Keysym::decode(&buffer[6..])?,
+                ))
+            }
+            id::GRABBED_KEY_PRESS => KeyInput::decode(&buffer[4..]).map(Event::GrabbedKeyPress),
+            id::GRABBED_KEY_RELEASE => KeyInput::decode(&buffer[4..]).map(Event::GrabbedKeyRelease),
+            id::KEYBOARD_GRAB_END => Ok(Event::KeyboardGrabEnd),
             _ => Err(DecodeError::BadFormat),
         }
     }
@@ -68,18 +258,86 @@ impl Encode for Event {
 
         match self {
             Self::KeyPress(key) | Self::KeyRelease(key) => key.encode_to(&mut buffer[4..]),
-            Self::WindowAdd(window) | Self::WindowRemove(window) => {
-                window.encode_to(&mut buffer[4..])
+            Self::WindowAdd(window, screen) | Self::WindowRemove(window, screen) => {
+                window.encode_to(&mut buffer[4..])?;
+                screen.encode_to(&mut buffer[8..])
             }
             Self::WindowConfigure(window) => window.encode_to(&mut buffer[4..]),
+            Self::ClientRequest(request) => request.encode_to(&mut buffer[4..]),
+            Self::WorkspaceChange { previous, current } => {
+                previous.encode_to(&mut buffer[4..])?;
+                current.encode_to(&mut buffer[8..])
+            }
+            Self::MonitorsChanged { screen, monitors } => {
+                screen.encode_to(&mut buffer[4..])?;
+                monitors.encode_to(&mut buffer[8..])
+            }
+            Self::WindowTitleChanged(window)
+            | Self::PointerEnter(window)
+            | Self::PointerLeave(window) => window.encode_to(&mut buffer[4..]),
+            Self::WindowUrgencyChanged { window, urgent } => {
+                window.encode_to(&mut buffer[4..])?;
+                (*urgent as u8).encode_to(&mut buffer[8..])
+            }
+            Self::WindowPropertyChanged { window, atom } => {
+                window.encode_to(&mut buffer[4..])?;
+                atom.encode_to(&mut buffer[8..])
+            }
+            Self::WindowFocus { previous, current } => {
+                previous.unwrap_or(0).encode_to(&mut buffer[4..])?;
+                current.unwrap_or(0).encode_to(&mut buffer[8..])
+            }
+            Self::ButtonPress(button, pointer)
+            | Self::ButtonRelease(button, pointer)
+            | Self::WindowClicked(button, pointer) => {
+                button.encode_to(&mut buffer[4..])?;
+                pointer.encode_to(&mut buffer[4 + button.encoded_size()..])
+            }
+            Self::PointerMotion(pointer) => pointer.encode_to(&mut buffer[4..]),
+            Self::KeySymPress(modmask, keysym) => {
+                u16::from(*modmask).encode_to(&mut buffer[4..])?;
+                keysym.encode_to(&mut buffer[6..])
+            }
+            Self::GrabbedKeyPress(input) | Self::GrabbedKeyRelease(input) => {
+                input.encode_to(&mut buffer[4..])
+            }
+            Self::KeyboardGrabEnd => Ok(()),
         }
     }
 
     fn encoded_size(&self) -> usize {
         match self {
             Self::KeyPress(key) | Self::KeyRelease(key) => 4 + key.encoded_size(),
-            Self::WindowAdd(window) | Self::WindowRemove(window) => 4 + window.encoded_size(),
+            Self::WindowAdd(window, screen) | Self::WindowRemove(window, screen) => {
+                4 + window.encoded_size() + screen.encoded_size()
+            }
             Self::WindowConfigure(window) => 4 + window.encoded_size(),
+            Self::ClientRequest(request) => 4 + request.encoded_size(),
+            Self::WorkspaceChange { previous, current } => {
+                4 + previous.encoded_size() + current.encoded_size()
+            }
+            Self::MonitorsChanged { screen, monitors } => {
+                4 + screen.encoded_size() + monitors.encoded_size()
+            }
+            Self::WindowTitleChanged(window)
+            | Self::PointerEnter(window)
+            | Self::PointerLeave(window) => 4 + window.encoded_size(),
+            Self::WindowUrgencyChanged { window, .. } => 4 + window.encoded_size() + 1,
+            Self::WindowPropertyChanged { window, atom } => {
+                4 + window.encoded_size() + atom.encoded_size()
+            }
+            Self::WindowFocus { .. } => 12,
+            Self::ButtonPress(button, pointer)
+            | Self::ButtonRelease(button, pointer)
+            | Self::WindowClicked(button, pointer) => {
+                4 + button.encoded_size() + pointer.encoded_size()
+            }
+            Self::PointerMotion(pointer) => 4 + pointer.encoded_size(),
+            Self::KeySymPress(_, keysym) => 4 + 2 + keysym.encoded_size(),
+            Self::GrabbedKeyPress(input) | Self::GrabbedKeyRelease(input) => {
+                4 + input.encoded_size()
+            }
+            Self::KeyboardGrabEnd => 4,
         }
     }
 }
@@ -91,6 +349,26 @@ pub enum SubscriptionEvent {
     WindowAdd,
     WindowRemove,
     WindowConfigure,
+    ClientRequest(ClientRequestKind),
+    WorkspaceChange,
+    MonitorsChanged,
+    WindowTitleChanged,
+    WindowUrgencyChanged,
+    WindowPropertyChanged,
+    PointerEnter,
+    PointerLeave,
+    WindowFocus,
+    ButtonPress(Button),
+    ButtonRelease,
+    PointerMotion,
+    KeySymPress(ModMask, Keysym),
+    /// Delivered to the plugin that holds the keyboard grab regardless of subscriptions.
+    GrabbedKeyPress,
+    /// Delivered to the plugin that holds the keyboard grab regardless of subscriptions.
+    GrabbedKeyRelease,
+    /// Delivered to the plugin that held the keyboard grab regardless of subscriptions.
+    KeyboardGrabEnd,
+    WindowClicked,
 }
 
 impl SubscriptionEvent {
@@ -102,18 +380,60 @@ impl SubscriptionEvent {
             WindowAdd => id::WINDOW_ADD,
             WindowRemove => id::WINDOW_REMOVE,
             WindowConfigure => id::WINDOW_CONFIGURE,
+            ClientRequest(_) => id::CLIENT_REQUEST,
+            WorkspaceChange => id::WORKSPACE_CHANGE,
+            MonitorsChanged => id::MONITORS_CHANGED,
+            WindowTitleChanged => id::WINDOW_TITLE_CHANGED,
+            WindowUrgencyChanged => id::WINDOW_URGENCY_CHANGED,
+            WindowPropertyChanged => id::WINDOW_PROPERTY_CHANGED,
+            PointerEnter => id::POINTER_ENTER,
+            PointerLeave => id::POINTER_LEAVE,
+            WindowFocus => id::WINDOW_FOCUS,
+            ButtonPress(_) => id::BUTTON_PRESS,
+            ButtonRelease => id::BUTTON_RELEASE,
+            PointerMotion => id::POINTER_MOTION,
+            KeySymPress(..) => id::KEY_SYM_PRESS,
+            GrabbedKeyPress => id::GRABBED_KEY_PRESS,
+            GrabbedKeyRelease => id::GRABBED_KEY_RELEASE,
+            KeyboardGrabEnd => id::KEYBOARD_GRAB_END,
+            WindowClicked => id::WINDOW_CLICKED,
         }
     }
 }
 
+impl From<KeyBinding> for SubscriptionEvent {
+    fn from(binding: KeyBinding) -> Self {
+        SubscriptionEvent::KeySymPress(binding.modmask, binding.keysym)
+    }
+}
+
 impl From<&Event> for SubscriptionEvent {
     fn from(event: &Event) -> Self {
         match event {
             Event::KeyPress(key) => SubscriptionEvent::KeyPress(*key),
             Event::KeyRelease(key) => SubscriptionEvent::KeyRelease(*key),
-            Event::WindowAdd(_) => SubscriptionEvent::WindowAdd,
-            Event::WindowRemove(_) => SubscriptionEvent::WindowRemove,
+            Event::WindowAdd(..) => SubscriptionEvent::WindowAdd,
+            Event::WindowRemove(..) => SubscriptionEvent::WindowRemove,
             Event::WindowConfigure(_) => SubscriptionEvent::WindowConfigure,
+            Event::ClientRequest(request) => SubscriptionEvent::ClientRequest(request.kind()),
+            Event::WorkspaceChange { .. } => SubscriptionEvent::WorkspaceChange,
+            Event::MonitorsChanged { .. } => SubscriptionEvent::MonitorsChanged,
+            Event::WindowTitleChanged(_) => SubscriptionEvent::WindowTitleChanged,
+            Event::WindowUrgencyChanged { .. } => SubscriptionEvent::WindowUrgencyChanged,
+            Event::WindowPropertyChanged { .. } => SubscriptionEvent::WindowPropertyChanged,
+            Event::PointerEnter(_) => SubscriptionEvent::PointerEnter,
+            Event::PointerLeave(_) => SubscriptionEvent::PointerLeave,
+            Event::WindowFocus { .. } => SubscriptionEvent::WindowFocus,
+            Event::ButtonPress(button, _) => SubscriptionEvent::ButtonPress(*button),
+            Event::ButtonRelease(..) => SubscriptionEvent::ButtonRelease,
+            Event::PointerMotion(_) => SubscriptionEvent::PointerMotion,
+            Event::KeySymPress(modmask, keysym) => {
+                SubscriptionEvent::KeySymPress(*modmask, *keysym)
+            }
+            Event::GrabbedKeyPress(_) => SubscriptionEvent::GrabbedKeyPress,
+            Event::GrabbedKeyRelease(_) => SubscriptionEvent::GrabbedKeyRelease,
+            Event::KeyboardGrabEnd => SubscriptionEvent::KeyboardGrabEnd,
+            Event::WindowClicked(..) => SubscriptionEvent::WindowClicked,
         }
     }
 }
@@ -128,7 +448,29 @@ impl Encode for SubscriptionEvent {
 
         match self {
             KeyPress(key) | KeyRelease(key) => key.encode_to(&mut buffer[4..])?,
-            WindowAdd | WindowRemove | WindowConfigure => {}
+            ClientRequest(kind) => kind.encode_to(&mut buffer[4..])?,
+            ButtonPress(button) => button.encode_to(&mut buffer[4..])?,
+            KeySymPress(modmask, keysym) => {
+                u16::from(*modmask).encode_to(&mut buffer[4..])?;
+                keysym.encode_to(&mut buffer[6..])?;
+            }
+            WindowAdd
+            | WindowRemove
+            | WindowConfigure
+            | WorkspaceChange
+            | MonitorsChanged
+            | WindowTitleChanged
+            | WindowUrgencyChanged
+            | WindowPropertyChanged
+            | PointerEnter
+            | PointerLeave
+            | WindowFocus
+            | ButtonRelease
+            | PointerMotion
+            | GrabbedKeyPress
+            | GrabbedKeyRelease
+            | KeyboardGrabEnd
+            | WindowClicked => {}
         }
 
         Ok(())
@@ -139,7 +481,26 @@ impl Encode for SubscriptionEvent {
 
         match self {
             KeyPress(key) | KeyRelease(key) => 4 + key.encoded_size(),
-            WindowAdd | WindowRemove | WindowConfigure => 4,
+            ClientRequest(kind) => 4 + kind.encoded_size(),
+            ButtonPress(button) => 4 + button.encoded_size(),
+            KeySymPress(_, keysym) => 4 + 2 + keysym.encoded_size(),
+            WindowAdd
+            | WindowRemove
+            | WindowConfigure
+            | WorkspaceChange
+            | MonitorsChanged
+            | WindowTitleChanged
+            | WindowUrgencyChanged
+            | WindowPropertyChanged
+            | PointerEnter
+            | PointerLeave
+            | WindowFocus
+            | ButtonRelease
+            | PointerMotion
+            | GrabbedKeyPress
+            | GrabbedKeyRelease
+            | KeyboardGrabEnd
+            | WindowClicked => 4,
         }
     }
 }
@@ -150,7 +511,8 @@ impl Decode for SubscriptionEvent {
     fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
         use SubscriptionEvent::*;
 
-        let id = u32::decode(buffer)?;
+        // Flags of the subscription are not a part of the event
+        let id = u32::decode(buffer)? & !subscription_flag::MASK;
 
         match id {
             id::KEY_PRESS => Key::decode(&buffer[4..]).map(KeyPress),
@@ -158,16 +520,66 @@ impl Decode for SubscriptionEvent {
             id::WINDOW_ADD => Ok(WindowAdd),
             id::WINDOW_REMOVE => Ok(WindowRemove),
             id::WINDOW_CONFIGURE => Ok(WindowConfigure),
+            id::CLIENT_REQUEST => ClientRequestKind::decode(&buffer[4..]).map(ClientRequest),
+            id::WORKSPACE_CHANGE => Ok(WorkspaceChange),
+            id::MONITORS_CHANGED => Ok(MonitorsChanged),
+            id::WINDOW_TITLE_CHANGED => Ok(WindowTitleChanged),
+            id::WINDOW_URGENCY_CHANGED => Ok(WindowUrgencyChanged),
+            id::WINDOW_PROPERTY_CHANGED => Ok(WindowPropertyChanged),
+            id::POINTER_ENTER => Ok(PointerEnter),
+            id::POINTER_LEAVE => Ok(PointerLeave),
+            id::WINDOW_FOCUS => Ok(WindowFocus),
+            id::BUTTON_PRESS => Button::decode(&buffer[4..]).map(ButtonPress),
+            id::BUTTON_RELEASE => Ok(ButtonRelease),
+            id::POINTER_MOTION => Ok(PointerMotion),
+            id::KEY_SYM_PRESS => Ok(KeySymPress(
+                u16::decode(&buffer[4..])?.into(),
+                Keysym::decode(&buffer[6..])?,
+            )),
+            id::GRABBED_KEY_PRESS => Ok(GrabbedKeyPress),
+            id::GRABBED_KEY_RELEASE => Ok(GrabbedKeyRelease),
+            id::KEYBOARD_GRAB_END => Ok(KeyboardGrabEnd),
+            id::WINDOW_CLICKED => Ok(WindowClicked),
             _ => Err(DecodeError::BadFormat),
         }
     }
 }
 
+/// Event subscription, optionally narrowed down by filters.
+///
+/// Encoded as the [SubscriptionEvent] with [subscription_flag]s in the upper bits of its id,
+/// followed by each filter: `<filter_id: u32>` (see [filter_id]) and its payload. Strings are
+/// encoded as `<len: u32>` followed by UTF-8 bytes.
 #[cfg(feature = "std")]
 #[derive(Debug, PartialEq, Eq)]
 pub struct Subscription {
     pub event: SubscriptionEvent,
+    /// Event is delivered only if it passes all of the filters.
     pub filters: Vec<SubscriptionFilter>,
+    /// Key press is grabbed synchronously and replayed to the focused client unless a plugin
+    /// consumes it. Only applies to `KeyPress` and `KeySymPress`.
+    pub conditional: bool,
+}
+
+#[cfg(feature = "std")]
+impl Subscription {
+    pub fn with_filter(mut self, filter: SubscriptionFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn conditional(mut self) -> Self {
+        self.conditional = true;
+        self
+    }
+
+    fn flags(&self) -> u32 {
+        if self.conditional {
+            subscription_flag::CONDITIONAL
+        } else {
+            0
+        }
+    }
 }
 
 #[cfg(feature = "std")]
@@ -176,6 +588,7 @@ impl From<SubscriptionEvent> for Subscription {
         Self {
             event,
             filters: vec![],
+            conditional: false,
         }
     }
 }
@@ -185,11 +598,27 @@ impl Encode for Subscription {
     type Error = EncodeError;
 
     fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
-        self.event.encode_to(buffer)
+        if buffer.len() < self.encoded_size() {
+            return Err(EncodeError::BufferSize);
+        }
+
+        self.event.encode_to(buffer)?;
+        (self.event.id() | self.flags()).encode_to(&mut buffer[0..])?;
+        let mut offset = self.event.encoded_size();
+        for filter in &self.filters {
+            filter.encode_to(&mut buffer[offset..])?;
+            offset += filter.encoded_size();
+        }
+        Ok(())
     }
 
     fn encoded_size(&self) -> usize {
         self.event.encoded_size()
+            + self
+                .filters
+                .iter()
+                .map(SubscriptionFilter::encoded_size)
+                .sum::<usize>()
     }
 }
 
@@ -199,11 +628,201 @@ impl Decode for Subscription {
 
     fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
         let event = SubscriptionEvent::decode(buffer)?;
+        let flags = u32::decode(buffer)? & subscription_flag::MASK;
+
+        let mut offset = event.encoded_size();
+        let mut filters = Vec::new();
+        while offset < buffer.len() {
+            let filter = SubscriptionFilter::decode(&buffer[offset..])?;
+            offset += filter.encoded_size();
+            filters.push(filter);
+        }
 
-        Ok(Self::from(event))
+        Ok(Self {
+            event,
+            filters,
+            conditional: flags & subscription_flag::CONDITIONAL != 0,
+        })
     }
 }
 
+/// Condition on the window an event concerns. Key events concern the focused window, pointer
+/// events concern the client under the pointer. Events without a window never pass filters other
+/// than [Workspace](SubscriptionFilter::Workspace) on `WorkspaceChange`.
 #[cfg(feature = "std")]
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub enum SubscriptionFilter {}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SubscriptionFilter {
+    Window(WindowId),
+    /// Class part of `WM_CLASS` is equal to the value.
+    Class(String),
+    /// Instance part of `WM_CLASS` is equal to the value.
+    Instance(String),
+    /// Window title matches a glob pattern, where `*` matches any sequence of characters and `?`
+    /// matches a single character.
+    Title(String),
+    WindowType(WindowType),
+    /// Center of the window is on the monitor with this index in the monitor list of its screen.
+    Monitor(u32),
+    /// Window is on the workspace or is visible on all workspaces. For `WorkspaceChange`, the
+    /// new workspace is this one.
+    Workspace(WorkspaceId),
+}
+
+#[cfg(feature = "std")]
+impl SubscriptionFilter {
+    pub fn id(&self) -> u32 {
+        use SubscriptionFilter::*;
+
+        match self {
+            Window(_) => filter_id::WINDOW,
+            Class(_) => filter_id::CLASS,
+            Instance(_) => filter_id::INSTANCE,
+            Title(_) => filter_id::TITLE,
+            Self::WindowType(_) => filter_id::WINDOW_TYPE,
+            Monitor(_) => filter_id::MONITOR,
+            Workspace(_) => filter_id::WORKSPACE,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Encode for SubscriptionFilter {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        use SubscriptionFilter::*;
+
+        if buffer.len() < self.encoded_size() {
+            return Err(EncodeError::BufferSize);
+        }
+
+        self.id().encode_to(&mut buffer[0..])?;
+        match self {
+            Window(id) | Monitor(id) | Workspace(id) => id.encode_to(&mut buffer[4..]),
+            Class(value) | Instance(value) | Title(value) => {
+                (value.len() as u32).encode_to(&mut buffer[4..])?;
+                buffer[8..8 + value.len()].copy_from_slice(value.as_bytes());
+                Ok(())
+            }
+            Self::WindowType(window_type) => (*window_type as u8).encode_to(&mut buffer[4..]),
+        }
+    }
+
+    fn encoded_size(&self) -> usize {
+        use SubscriptionFilter::*;
+
+        match self {
+            Window(_) | Monitor(_) | Workspace(_) => 4 + 4,
+            Class(value) | Instance(value) | Title(value) => 4 + 4 + value.len(),
+            Self::WindowType(_) => 4 + 1,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Decode for SubscriptionFilter {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        use SubscriptionFilter::*;
+
+        // Numbers are decoded from exact slices, so that short buffers are not indexed out of
+        // bounds
+        let number = |range: core::ops::Range<usize>| {
+            buffer
+                .get(range)
+                .ok_or(DecodeError::BadFormat)
+                .and_then(u32::decode)
+        };
+        let string = || {
+            let len = number(4..8)? as usize;
+            let bytes = buffer.get(8..8 + len).ok_or(DecodeError::BadFormat)?;
+            String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::BadFormat)
+        };
+
+        match number(0..4)? {
+            filter_id::WINDOW => number(4..8).map(Window),
+            filter_id::CLASS => string().map(Class),
+            filter_id::INSTANCE => string().map(Instance),
+            filter_id::TITLE => string().map(Title),
+            filter_id::WINDOW_TYPE => buffer
+                .get(4)
+                .and_then(|&value| crate::window::WindowType::from_u8(value))
+                .map(Self::WindowType)
+                .ok_or(DecodeError::BadFormat),
+            filter_id::MONITOR => number(4..8).map(Monitor),
+            filter_id::WORKSPACE => number(4..8).map(Workspace),
+            _ => Err(DecodeError::BadFormat),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::encoding::EncodeExt;
+    use crate::key::Keycode;
+
+    fn round_trip(subscription: Subscription) {
+        let buffer = subscription.encode_to_vec().unwrap();
+        assert_eq!(buffer.len(), subscription.encoded_size());
+        assert_eq!(Subscription::decode(&buffer).unwrap(), subscription);
+    }
+
+    #[test]
+    fn filter_round_trip() {
+        let filters = [
+            SubscriptionFilter::Window(0x0040_0001),
+            SubscriptionFilter::Class("Firefox".into()),
+            SubscriptionFilter::Instance("".into()),
+            SubscriptionFilter::Title("* — Mozilla Firefox".into()),
+            SubscriptionFilter::WindowType(WindowType::Dialog),
+            SubscriptionFilter::Monitor(1),
+            SubscriptionFilter::Workspace(3),
+        ];
+        for filter in filters {
+            let buffer = filter.encode_to_vec().unwrap();
+            assert_eq!(buffer.len(), filter.encoded_size());
+            assert_eq!(SubscriptionFilter::decode(&buffer).unwrap(), filter);
+        }
+    }
+
+    #[test]
+    fn filter_decode_truncated() {
+        let buffer = SubscriptionFilter::Class("Firefox".into())
+            .encode_to_vec()
+            .unwrap();
+        assert!(SubscriptionFilter::decode(&buffer[..buffer.len() - 1]).is_err());
+        assert!(SubscriptionFilter::decode(&buffer[..6]).is_err());
+        assert!(SubscriptionFilter::decode(&[0xff; 8]).is_err());
+    }
+
+    #[test]
+    fn subscription_round_trip() {
+        round_trip(Subscription::from(SubscriptionEvent::WindowAdd));
+        round_trip(
+            Subscription::from(SubscriptionEvent::WindowFocus)
+                .with_filter(SubscriptionFilter::Class("URxvt".into()))
+                .with_filter(SubscriptionFilter::Workspace(0)),
+        );
+        round_trip(
+            Subscription::from(SubscriptionEvent::KeyPress(Key::new(
+                ModMask::CONTROL,
+                Keycode::from(38),
+            )))
+            .with_filter(SubscriptionFilter::Title("*vim*".into()))
+            .conditional(),
+        );
+    }
+
+    #[test]
+    fn subscription_without_flags() {
+        // Subscriptions encoded without flags, but with filters, decode as unconditional
+        let mut buffer = SubscriptionEvent::WindowAdd.encode_to_vec().unwrap();
+        buffer.extend(SubscriptionFilter::Monitor(2).encode_to_vec().unwrap());
+
+        let subscription = Subscription::decode(&buffer).unwrap();
+        assert!(!subscription.conditional);
+        assert_eq!(subscription.filters, vec![SubscriptionFilter::Monitor(2)]);
+    }
+}
@@ -1,5 +1,9 @@
 use crate::encoding::{Decode, DecodeError, Encode, EncodeError};
+use core::fmt;
 use core::ops::{BitOr, BitOrAssign};
+use core::str::FromStr;
+
+mod names;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Key {
@@ -58,7 +62,7 @@ impl Encode for Key {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct ModMask(u16);
 
 impl ModMask {
@@ -70,7 +74,23 @@ impl ModMask {
     pub const M3: Self = Self(1 << 5);
     pub const M4: Self = Self(1 << 6);
     pub const M5: Self = Self(1 << 7);
+    pub const BUTTON1: Self = Self(1 << 8);
+    pub const BUTTON2: Self = Self(1 << 9);
+    pub const BUTTON3: Self = Self(1 << 10);
+    pub const BUTTON4: Self = Self(1 << 11);
+    pub const BUTTON5: Self = Self(1 << 12);
+    /// Matches any combination of modifiers when used in a binding.
     pub const ANY: Self = Self(1 << 15);
+
+    /// Check whether all bits of `other` are set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Clear the bits of `other`.
+    pub fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
 }
 
 impl From<ModMask> for u16 {
@@ -87,6 +107,73 @@ impl From<u16> for ModMask {
     }
 }
 
+impl ModMask {
+    /// Modifier names used by [Display](fmt::Display), in order of bits.
+    const NAMES: [(Self, &'static str); 14] = [
+        (Self::SHIFT, "Shift"),
+        (Self::LOCK, "Lock"),
+        (Self::CONTROL, "Ctrl"),
+        (Self::M1, "Alt"),
+        (Self::M2, "Mod2"),
+        (Self::M3, "Mod3"),
+        (Self::M4, "Super"),
+        (Self::M5, "Mod5"),
+        (Self::BUTTON1, "Button1"),
+        (Self::BUTTON2, "Button2"),
+        (Self::BUTTON3, "Button3"),
+        (Self::BUTTON4, "Button4"),
+        (Self::BUTTON5, "Button5"),
+        (Self::ANY, "Any"),
+    ];
+
+    /// Alternative modifier names accepted by [FromStr].
+    const ALIASES: [(Self, &'static str); 9] = [
+        (Self::LOCK, "CapsLock"),
+        (Self::CONTROL, "Control"),
+        (Self::M1, "Mod1"),
+        (Self::M1, "Meta"),
+        (Self::M4, "Mod4"),
+        (Self::M4, "Win"),
+        (Self::M4, "Logo"),
+        (Self::M4, "Hyper"),
+        (Self::M5, "AltGr"),
+    ];
+}
+
+/// Parses `+`-separated modifier names, such as `Super+Shift`. Names are case-insensitive.
+impl FromStr for ModMask {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modmask = Self::default();
+        for name in s.split('+').map(str::trim) {
+            modmask |= Self::NAMES
+                .iter()
+                .chain(Self::ALIASES.iter())
+                .find(|(_, known)| known.eq_ignore_ascii_case(name))
+                .map(|&(modifier, _)| modifier)
+                .ok_or(ParseKeyError::Modifier)?;
+        }
+        Ok(modmask)
+    }
+}
+
+impl fmt::Display for ModMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = Self::NAMES
+            .iter()
+            .filter(|&&(modifier, _)| self.contains(modifier))
+            .map(|(_, name)| name);
+        if let Some(name) = names.next() {
+            f.write_str(name)?;
+        }
+        for name in names {
+            write!(f, "+{}", name)?;
+        }
+        Ok(())
+    }
+}
+
 impl BitOr for ModMask {
     type Output = Self;
 
@@ -101,6 +188,8 @@ impl BitOrAssign for ModMask {
     }
 }
 
+/// Hardware keycode. Constants correspond to the evdev keycodes of a QWERTY keyboard, prefer
+/// [Keysym] bindings that follow the current keyboard layout.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Keycode(u8);
 
@@ -344,3 +433,394 @@ impl From<Keycode> for i32 {
         keycode.0 as i32
     }
 }
+
+/// Layout-independent key symbol, as defined by `X11/keysymdef.h`. Letter constants are lowercase
+/// keysyms, which are found in the unshifted column of the keyboard mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Keysym(u32);
+
+#[allow(non_upper_case_globals)]
+impl Keysym {
+    pub const NoSymbol: Self = Self(0);
+    pub const One: Self = Self(0x31);
+    pub const Two: Self = Self(0x32);
+    pub const Three: Self = Self(0x33);
+    pub const Four: Self = Self(0x34);
+    pub const Five: Self = Self(0x35);
+    pub const Six: Self = Self(0x36);
+    pub const Seven: Self = Self(0x37);
+    pub const Eight: Self = Self(0x38);
+    pub const Nine: Self = Self(0x39);
+    pub const Zero: Self = Self(0x30);
+    pub const A: Self = Self(0x61);
+    pub const B: Self = Self(0x62);
+    pub const C: Self = Self(0x63);
+    pub const D: Self = Self(0x64);
+    pub const E: Self = Self(0x65);
+    pub const F: Self = Self(0x66);
+    pub const G: Self = Self(0x67);
+    pub const H: Self = Self(0x68);
+    pub const I: Self = Self(0x69);
+    pub const J: Self = Self(0x6a);
+    pub const K: Self = Self(0x6b);
+    pub const L: Self = Self(0x6c);
+    pub const M: Self = Self(0x6d);
+    pub const N: Self = Self(0x6e);
+    pub const O: Self = Self(0x6f);
+    pub const P: Self = Self(0x70);
+    pub const Q: Self = Self(0x71);
+    pub const R: Self = Self(0x72);
+    pub const S: Self = Self(0x73);
+    pub const T: Self = Self(0x74);
+    pub const U: Self = Self(0x75);
+    pub const V: Self = Self(0x76);
+    pub const W: Self = Self(0x77);
+    pub const X: Self = Self(0x78);
+    pub const Y: Self = Self(0x79);
+    pub const Z: Self = Self(0x7a);
+    pub const Space: Self = Self(0x20);
+    pub const Apostrophe: Self = Self(0x27);
+    pub const Comma: Self = Self(0x2c);
+    pub const Minus: Self = Self(0x2d);
+    pub const Period: Self = Self(0x2e);
+    pub const Slash: Self = Self(0x2f);
+    pub const Semicolon: Self = Self(0x3b);
+    pub const Equal: Self = Self(0x3d);
+    pub const BracketLeft: Self = Self(0x5b);
+    pub const Backslash: Self = Self(0x5c);
+    pub const BracketRight: Self = Self(0x5d);
+    pub const Grave: Self = Self(0x60);
+    pub const BackSpace: Self = Self(0xff08);
+    pub const Tab: Self = Self(0xff09);
+    pub const Return: Self = Self(0xff0d);
+    pub const Pause: Self = Self(0xff13);
+    pub const ScrollLock: Self = Self(0xff14);
+    pub const Escape: Self = Self(0xff1b);
+    pub const Home: Self = Self(0xff50);
+    pub const Left: Self = Self(0xff51);
+    pub const Up: Self = Self(0xff52);
+    pub const Right: Self = Self(0xff53);
+    pub const Down: Self = Self(0xff54);
+    pub const Prior: Self = Self(0xff55);
+    pub const Next: Self = Self(0xff56);
+    pub const End: Self = Self(0xff57);
+    pub const Print: Self = Self(0xff61);
+    pub const Insert: Self = Self(0xff63);
+    pub const Menu: Self = Self(0xff67);
+    pub const NumLock: Self = Self(0xff7f);
+    pub const F1: Self = Self(0xffbe);
+    pub const F2: Self = Self(0xffbf);
+    pub const F3: Self = Self(0xffc0);
+    pub const F4: Self = Self(0xffc1);
+    pub const F5: Self = Self(0xffc2);
+    pub const F6: Self = Self(0xffc3);
+    pub const F7: Self = Self(0xffc4);
+    pub const F8: Self = Self(0xffc5);
+    pub const F9: Self = Self(0xffc6);
+    pub const F10: Self = Self(0xffc7);
+    pub const F11: Self = Self(0xffc8);
+    pub const F12: Self = Self(0xffc9);
+    pub const ShiftL: Self = Self(0xffe1);
+    pub const ShiftR: Self = Self(0xffe2);
+    pub const ControlL: Self = Self(0xffe3);
+    pub const ControlR: Self = Self(0xffe4);
+    pub const CapsLock: Self = Self(0xffe5);
+    pub const AltL: Self = Self(0xffe9);
+    pub const AltR: Self = Self(0xffea);
+    pub const SuperL: Self = Self(0xffeb);
+    pub const SuperR: Self = Self(0xffec);
+    pub const Delete: Self = Self(0xffff);
+    pub const XF86MonBrightnessUp: Self = Self(0x1008ff02);
+    pub const XF86MonBrightnessDown: Self = Self(0x1008ff03);
+    pub const XF86AudioLowerVolume: Self = Self(0x1008ff11);
+    pub const XF86AudioMute: Self = Self(0x1008ff12);
+    pub const XF86AudioRaiseVolume: Self = Self(0x1008ff13);
+    pub const XF86AudioPlay: Self = Self(0x1008ff14);
+    pub const XF86AudioStop: Self = Self(0x1008ff15);
+    pub const XF86AudioPrev: Self = Self(0x1008ff16);
+    pub const XF86AudioNext: Self = Self(0x1008ff17);
+}
+
+impl From<u32> for Keysym {
+    fn from(keysym: u32) -> Self {
+        Self(keysym)
+    }
+}
+
+impl From<Keysym> for u32 {
+    fn from(keysym: Keysym) -> u32 {
+        keysym.0
+    }
+}
+
+impl Keysym {
+    /// Keysyms of Unicode characters outside of Latin-1 are offset by this value.
+    const UNICODE_OFFSET: u32 = 0x0100_0000;
+
+    /// Keysym of a character, following the Latin-1 and Unicode keysym ranges.
+    pub fn from_char(c: char) -> Self {
+        match c as u32 {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => Self(code),
+            code => Self(Self::UNICODE_OFFSET + code),
+        }
+    }
+
+    /// Character typed by the keysym, if it is a printable Latin-1 or Unicode keysym.
+    pub fn to_char(self) -> Option<char> {
+        match self.0 {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => char::from_u32(code),
+            code if code > Self::UNICODE_OFFSET => char::from_u32(code - Self::UNICODE_OFFSET),
+            _ => None,
+        }
+    }
+
+    /// Name as defined in `X11/keysymdef.h` without the `XK_` prefix, such as `Return` or
+    /// `KP_Add`.
+    pub fn name(&self) -> Option<&'static str> {
+        names::KEYSYM_NAMES
+            .iter()
+            .find(|&&(_, keysym)| keysym == self.0)
+            .map(|&(name, _)| name)
+    }
+}
+
+/// Parses X keysym names (`Return`, `KP_Add`, `XF86AudioMute`), single characters, Unicode
+/// keysyms (`U20AC`) and hexadecimal values (`0xff0d`).
+impl FromStr for Keysym {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(&(_, keysym)) = names::KEYSYM_NAMES.iter().find(|&&(name, _)| name == s) {
+            return Ok(Self(keysym));
+        }
+
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Self::from_char(c));
+        }
+
+        if let Some(hex) = s.strip_prefix("0x") {
+            u32::from_str_radix(hex, 16)
+                .map(Self)
+                .map_err(|_| ParseKeyError::Keysym)
+        } else if let Some(hex) = s.strip_prefix('U') {
+            u32::from_str_radix(hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .map(Self::from_char)
+                .ok_or(ParseKeyError::Keysym)
+        } else {
+            Err(ParseKeyError::Keysym)
+        }
+    }
+}
+
+impl fmt::Display for Keysym {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            // Only keysyms that parse back to the same value are written as characters
+            None if self.0 > Self::UNICODE_OFFSET
+                && self.to_char().map(Self::from_char) == Some(*self) =>
+            {
+                write!(f, "U{:04X}", self.0 - Self::UNICODE_OFFSET)
+            }
+            None => write!(f, "{:#x}", self.0),
+        }
+    }
+}
+
+impl Decode for Keysym {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        u32::decode(buffer).map(Self)
+    }
+}
+
+impl Encode for Keysym {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.0.encode_to(buffer)
+    }
+
+    fn encoded_size(&self) -> usize {
+        4
+    }
+}
+
+/// Key event reported during a keyboard grab, translated with the current keyboard mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyInput {
+    pub key: Key,
+    /// Keysym of the key for the active Shift and Caps Lock state.
+    pub keysym: Keysym,
+    /// Character typed by the key, if any.
+    pub text: Option<char>,
+}
+
+impl Decode for KeyInput {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() < 11 {
+            return Err(DecodeError::BadFormat);
+        }
+
+        Ok(Self {
+            key: Key::decode(&buffer[0..])?,
+            keysym: Keysym::decode(&buffer[3..])?,
+            text: char::from_u32(u32::decode(&buffer[7..])?).filter(|&c| c != '\0'),
+        })
+    }
+}
+
+impl Encode for KeyInput {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        if buffer.len() < self.encoded_size() {
+            return Err(EncodeError::BufferSize);
+        }
+
+        self.key.encode_to(&mut buffer[0..])?;
+        self.keysym.encode_to(&mut buffer[3..])?;
+        self.text.map_or(0, u32::from).encode_to(&mut buffer[7..])
+    }
+
+    fn encoded_size(&self) -> usize {
+        11
+    }
+}
+
+/// Modifiers and keysym of a key binding, written as `Super+Shift+Return`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub modmask: ModMask,
+    pub keysym: Keysym,
+}
+
+impl KeyBinding {
+    pub fn new(modmask: ModMask, keysym: Keysym) -> Self {
+        Self { modmask, keysym }
+    }
+}
+
+/// Parses modifiers followed by a key, separated with `+`. See [ModMask] and [Keysym] for
+/// accepted names. The `+` key itself is written as `Ctrl++`.
+impl FromStr for KeyBinding {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // The last character is always a part of the key, even if it is `+`
+        let last = s.char_indices().last().map_or(0, |(i, _)| i);
+        let (modmask, key) = match s[..last].rfind('+') {
+            Some(i) => (s[..i].parse()?, s[i + 1..].trim()),
+            None => (ModMask::default(), s),
+        };
+
+        Ok(Self {
+            modmask,
+            keysym: key.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modmask == ModMask::default() {
+            write!(f, "{}", self.keysym)
+        } else {
+            write!(f, "{}+{}", self.modmask, self.keysym)
+        }
+    }
+}
+
+/// Error returned when parsing a key binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseKeyError {
+    /// Unknown modifier name.
+    Modifier,
+    /// Key is neither a known keysym name, a single character nor a keysym value.
+    Keysym,
+}
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Modifier => f.write_str("unknown modifier"),
+            Self::Keysym => f.write_str("unknown key"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    fn binding(s: &str) -> KeyBinding {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_plus_key() {
+        assert_eq!(
+            binding("Ctrl++"),
+            KeyBinding::new(ModMask::CONTROL, Keysym::from(0x2b))
+        );
+        assert_eq!(
+            binding("+"),
+            KeyBinding::new(ModMask::default(), Keysym::from(0x2b))
+        );
+        assert_eq!(binding("Ctrl+plus"), binding("Ctrl++"));
+    }
+
+    #[test]
+    fn parse_keysym_values() {
+        assert_eq!(binding("U20AC").keysym, Keysym::from_char('€'));
+        assert_eq!(binding("U20AC").keysym.to_char(), Some('€'));
+        assert_eq!(binding("0xff0d").keysym, Keysym::Return);
+        assert_eq!("Uzz".parse::<KeyBinding>(), Err(ParseKeyError::Keysym));
+        assert_eq!("0x".parse::<KeyBinding>(), Err(ParseKeyError::Keysym));
+    }
+
+    #[test]
+    fn parse_case() {
+        // Modifier names are case-insensitive
+        assert_eq!(binding("super+SHIFT+a"), binding("Super+Shift+a"));
+        assert_eq!(binding("control+Return").modmask, ModMask::CONTROL);
+        // Keysym names are not, and single characters keep their case
+        assert_eq!(
+            "Super+return".parse::<KeyBinding>(),
+            Err(ParseKeyError::Keysym)
+        );
+        assert_eq!(binding("Super+a").keysym, Keysym::from(0x61));
+        assert_eq!(binding("Super+A").keysym, Keysym::from(0x41));
+        assert_eq!("Supr+a".parse::<KeyBinding>(), Err(ParseKeyError::Modifier));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let bindings = [
+            KeyBinding::new(ModMask::M4 | ModMask::SHIFT, Keysym::Return),
+            KeyBinding::new(ModMask::CONTROL, Keysym::from(0x2b)),
+            KeyBinding::new(ModMask::default(), Keysym::from_char('€')),
+            KeyBinding::new(ModMask::M1, Keysym::from(0x1234_5678)),
+            // Unicode keysym of a Latin-1 character
+            KeyBinding::new(ModMask::M1, Keysym::from(0x0100_0041)),
+            KeyBinding::new(ModMask::ANY, Keysym::from_char('q')),
+        ];
+        for binding in bindings {
+            let s = binding.to_string();
+            assert_eq!(s.parse::<KeyBinding>(), Ok(binding), "{}", s);
+        }
+        assert_eq!(
+            binding("super+shift+Return").to_string(),
+            "Shift+Super+Return"
+        );
+        assert_eq!(binding("+").to_string(), "plus");
+        assert_eq!(binding("U20AC").to_string(), "U20AC");
+    }
+}
@@ -1,7 +1,9 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod button;
 pub mod encoding;
 pub mod event;
 pub mod key;
+pub mod monitor;
 pub mod subscription;
 pub mod window;
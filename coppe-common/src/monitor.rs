@@ -0,0 +1,150 @@
+use crate::encoding::*;
+use crate::window::Geometry;
+
+/// Maximum number of monitors that can be reported to plugins.
+pub const MAX_MONITORS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Monitor {
+    pub geometry: Geometry,
+    pub primary: bool,
+}
+
+impl Monitor {
+    const EMPTY: Self = Self {
+        geometry: Geometry {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        },
+        primary: false,
+    };
+}
+
+impl Encode for Monitor {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.geometry.encode_to(&mut buffer[0..])?;
+        (self.primary as u8).encode_to(&mut buffer[self.geometry.encoded_size()..])
+    }
+
+    fn encoded_size(&self) -> usize {
+        self.geometry.encoded_size() + 1
+    }
+}
+
+impl Decode for Monitor {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() < 9 {
+            return Err(DecodeError::BadFormat);
+        }
+        Ok(Self {
+            geometry: Geometry::decode(&buffer[0..])?,
+            primary: u8::decode(&buffer[8..])? != 0,
+        })
+    }
+}
+
+/// Fixed-capacity list of monitors. Monitors beyond [MAX_MONITORS] are dropped.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MonitorList {
+    len: u8,
+    monitors: [Monitor; MAX_MONITORS],
+}
+
+impl MonitorList {
+    /// Size of the largest possible encoded list.
+    pub const MAX_ENCODED_SIZE: usize = 1 + MAX_MONITORS * 9;
+
+    pub const fn new() -> Self {
+        Self {
+            len: 0,
+            monitors: [Monitor::EMPTY; MAX_MONITORS],
+        }
+    }
+
+    /// Append a monitor. Returns `false` if the list is full.
+    pub fn push(&mut self, monitor: Monitor) -> bool {
+        if self.len() == MAX_MONITORS {
+            return false;
+        }
+        self.monitors[self.len()] = monitor;
+        self.len += 1;
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_slice(&self) -> &[Monitor] {
+        &self.monitors[..self.len()]
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, Monitor> {
+        self.as_slice().iter()
+    }
+
+    /// Primary monitor, or the first one if none is marked as primary.
+    pub fn primary(&self) -> Option<&Monitor> {
+        self.iter()
+            .find(|monitor| monitor.primary)
+            .or_else(|| self.as_slice().first())
+    }
+}
+
+impl Default for MonitorList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for MonitorList {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Encode for MonitorList {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        if buffer.len() < self.encoded_size() {
+            return Err(EncodeError::BufferSize);
+        }
+        self.len.encode_to(&mut buffer[0..])?;
+        for (i, monitor) in self.iter().enumerate() {
+            monitor.encode_to(&mut buffer[1 + i * 9..])?;
+        }
+        Ok(())
+    }
+
+    fn encoded_size(&self) -> usize {
+        1 + self.len() * 9
+    }
+}
+
+impl Decode for MonitorList {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        let len = *buffer.first().ok_or(DecodeError::BadFormat)? as usize;
+        if len > MAX_MONITORS || buffer.len() < 1 + len * 9 {
+            return Err(DecodeError::BadFormat);
+        }
+
+        let mut list = Self::new();
+        for i in 0..len {
+            list.push(Monitor::decode(&buffer[1 + i * 9..])?);
+        }
+        Ok(list)
+    }
+}
@@ -1,10 +1,17 @@
 use crate::encoding::*;
 
 pub type WindowId = u32;
+pub type WorkspaceId = u32;
+/// X screen number.
+pub type ScreenId = u32;
+
+/// Pseudo-workspace for windows that are shown on all workspaces.
+pub const ALL_WORKSPACES: WorkspaceId = u32::MAX;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Window {
     pub id: WindowId,
+    pub screen: ScreenId,
     pub geometry: Geometry,
 }
 
@@ -13,12 +20,12 @@ impl Encode for Window {
 
     fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
         self.id.encode_to(&mut buffer[0..])?;
-        self.geometry
-            .encode_to(&mut buffer[self.id.encoded_size()..])
+        self.screen.encode_to(&mut buffer[4..])?;
+        self.geometry.encode_to(&mut buffer[8..])
     }
 
     fn encoded_size(&self) -> usize {
-        self.id.encoded_size() + self.geometry.encoded_size()
+        self.id.encoded_size() + self.screen.encoded_size() + self.geometry.encoded_size()
     }
 }
 
@@ -26,9 +33,17 @@ impl Decode for Window {
     type Error = DecodeError;
 
     fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() < 16 {
+            return Err(DecodeError::BadFormat);
+        }
         let id = WindowId::decode(&buffer[0..])?;
-        let geometry = Geometry::decode(&buffer[id.encoded_size()..])?;
-        Ok(Self { id, geometry })
+        let screen = ScreenId::decode(&buffer[4..])?;
+        let geometry = Geometry::decode(&buffer[8..])?;
+        Ok(Self {
+            id,
+            screen,
+            geometry,
+        })
     }
 }
 
@@ -67,3 +82,639 @@ impl Decode for Geometry {
         })
     }
 }
+
+/// Mapping state of a managed window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MapState {
+    /// Window is shown.
+    Mapped = 1,
+    /// Window is on a workspace that is not shown.
+    Hidden = 2,
+    /// Window is minimized.
+    Iconic = 3,
+}
+
+impl MapState {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Mapped),
+            2 => Some(Self::Hidden),
+            3 => Some(Self::Iconic),
+            _ => None,
+        }
+    }
+}
+
+/// Built-in focus policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FocusPolicy {
+    /// Focus and raise the window under the pointer. Focus is dropped when the pointer moves to
+    /// the root window.
+    #[default]
+    FollowMouse = 0,
+    /// Focus the window under the pointer without raising it. Focus is kept when the pointer
+    /// moves to the root window.
+    Sloppy = 1,
+    /// Focus and raise windows when clicked.
+    Click = 2,
+    /// Never change focus. Focus is managed by plugins.
+    None = 3,
+}
+
+impl FocusPolicy {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::FollowMouse),
+            1 => Some(Self::Sloppy),
+            2 => Some(Self::Click),
+            3 => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// Size constraints from ICCCM `WM_NORMAL_HINTS`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SizeHints {
+    pub min_size: Option<(u16, u16)>,
+    pub max_size: Option<(u16, u16)>,
+    pub base_size: Option<(u16, u16)>,
+    pub increment: Option<(u16, u16)>,
+    /// Minimum and maximum aspect ratio as `(numerator, denominator)` pairs.
+    pub aspect: Option<((u32, u32), (u32, u32))>,
+}
+
+impl SizeHints {
+    const ENCODED_SIZE: usize = 33;
+
+    /// Adjust the size to the hints, following the algorithm described in ICCCM 4.1.2.3.
+    pub fn constrain(&self, width: u16, height: u16) -> (u16, u16) {
+        // ICCCM: base size defaults to min size and vice versa
+        let (base_w, base_h) = self.base_size.or(self.min_size).unwrap_or((0, 0));
+        let (min_w, min_h) = self.min_size.or(self.base_size).unwrap_or((1, 1));
+        let (base_w, base_h) = (i64::from(base_w), i64::from(base_h));
+
+        let mut w = i64::from(width) - base_w;
+        let mut h = i64::from(height) - base_h;
+
+        if let Some(((min_n, min_d), (max_n, max_d))) = self.aspect {
+            let (min_n, min_d) = (i64::from(min_n), i64::from(min_d));
+            let (max_n, max_d) = (i64::from(max_n), i64::from(max_d));
+            if min_n > 0 && min_d > 0 && w * min_d < h * min_n {
+                h = w * min_d / min_n;
+            }
+            if max_n > 0 && max_d > 0 && w * max_d > h * max_n {
+                w = h * max_n / max_d;
+            }
+        }
+
+        if let Some((inc_w, inc_h)) = self.increment {
+            if inc_w > 0 {
+                w -= w.rem_euclid(i64::from(inc_w));
+            }
+            if inc_h > 0 {
+                h -= h.rem_euclid(i64::from(inc_h));
+            }
+        }
+
+        let mut w = w + base_w;
+        let mut h = h + base_h;
+
+        w = w.max(i64::from(min_w));
+        h = h.max(i64::from(min_h));
+        if let Some((max_w, max_h)) = self.max_size {
+            if max_w > 0 {
+                w = w.min(i64::from(max_w));
+            }
+            if max_h > 0 {
+                h = h.min(i64::from(max_h));
+            }
+        }
+
+        (
+            w.clamp(1, i64::from(u16::MAX)) as u16,
+            h.clamp(1, i64::from(u16::MAX)) as u16,
+        )
+    }
+}
+
+impl Encode for SizeHints {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        if buffer.len() < Self::ENCODED_SIZE {
+            return Err(EncodeError::BufferSize);
+        }
+
+        let sizes = [self.min_size, self.max_size, self.base_size, self.increment];
+        let mut flags = 0u8;
+        for (i, size) in sizes.iter().enumerate() {
+            let (width, height) = size.unwrap_or((0, 0));
+            width.encode_to(&mut buffer[1 + i * 4..])?;
+            height.encode_to(&mut buffer[3 + i * 4..])?;
+            flags |= (size.is_some() as u8) << i;
+        }
+
+        let ((min_n, min_d), (max_n, max_d)) = self.aspect.unwrap_or(((0, 0), (0, 0)));
+        min_n.encode_to(&mut buffer[17..])?;
+        min_d.encode_to(&mut buffer[21..])?;
+        max_n.encode_to(&mut buffer[25..])?;
+        max_d.encode_to(&mut buffer[29..])?;
+        flags |= (self.aspect.is_some() as u8) << 4;
+
+        buffer[0] = flags;
+        Ok(())
+    }
+
+    fn encoded_size(&self) -> usize {
+        Self::ENCODED_SIZE
+    }
+}
+
+impl Decode for SizeHints {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() < Self::ENCODED_SIZE {
+            return Err(DecodeError::BadFormat);
+        }
+
+        let flags = buffer[0];
+        let size = |i: usize| -> Result<Option<(u16, u16)>, DecodeError> {
+            if flags & (1 << i) == 0 {
+                return Ok(None);
+            }
+            let width = u16::decode(&buffer[1 + i * 4..])?;
+            let height = u16::decode(&buffer[3 + i * 4..])?;
+            Ok(Some((width, height)))
+        };
+
+        let aspect = if flags & (1 << 4) != 0 {
+            Some((
+                (u32::decode(&buffer[17..])?, u32::decode(&buffer[21..])?),
+                (u32::decode(&buffer[25..])?, u32::decode(&buffer[29..])?),
+            ))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            min_size: size(0)?,
+            max_size: size(1)?,
+            base_size: size(2)?,
+            increment: size(3)?,
+            aspect,
+        })
+    }
+}
+
+/// Window state from EWMH `_NET_WM_STATE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowState {
+    Modal = 1,
+    Sticky = 2,
+    MaximizedVert = 3,
+    MaximizedHorz = 4,
+    Shaded = 5,
+    SkipTaskbar = 6,
+    SkipPager = 7,
+    Hidden = 8,
+    Fullscreen = 9,
+    Above = 10,
+    Below = 11,
+    DemandsAttention = 12,
+}
+
+impl WindowState {
+    pub const ALL: [WindowState; 12] = [
+        WindowState::Modal,
+        WindowState::Sticky,
+        WindowState::MaximizedVert,
+        WindowState::MaximizedHorz,
+        WindowState::Shaded,
+        WindowState::SkipTaskbar,
+        WindowState::SkipPager,
+        WindowState::Hidden,
+        WindowState::Fullscreen,
+        WindowState::Above,
+        WindowState::Below,
+        WindowState::DemandsAttention,
+    ];
+
+    fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|&state| state as u8 == value)
+    }
+}
+
+/// Window type from EWMH `_NET_WM_WINDOW_TYPE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowType {
+    Normal = 1,
+    Desktop = 2,
+    Dock = 3,
+    Toolbar = 4,
+    Menu = 5,
+    Utility = 6,
+    Splash = 7,
+    Dialog = 8,
+    DropdownMenu = 9,
+    PopupMenu = 10,
+    Tooltip = 11,
+    Notification = 12,
+    Combo = 13,
+    Dnd = 14,
+}
+
+impl WindowType {
+    pub const ALL: [WindowType; 14] = [
+        WindowType::Normal,
+        WindowType::Desktop,
+        WindowType::Dock,
+        WindowType::Toolbar,
+        WindowType::Menu,
+        WindowType::Utility,
+        WindowType::Splash,
+        WindowType::Dialog,
+        WindowType::DropdownMenu,
+        WindowType::PopupMenu,
+        WindowType::Tooltip,
+        WindowType::Notification,
+        WindowType::Combo,
+        WindowType::Dnd,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|&window_type| window_type as u8 == value)
+    }
+}
+
+/// Action requested for `_NET_WM_STATE` change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateAction {
+    Remove = 0,
+    Add = 1,
+    Toggle = 2,
+}
+
+/// Source indication of the EWMH request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestSource {
+    /// Request was sent by a legacy client without source indication.
+    Unknown = 0,
+    Application = 1,
+    /// Request was sent by a pager, taskbar or other tool acting on behalf of the user.
+    Pager = 2,
+}
+
+/// Direction of interactive move or resize, as defined by EWMH `_NET_WM_MOVERESIZE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveResizeDirection {
+    SizeTopLeft = 0,
+    SizeTop = 1,
+    SizeTopRight = 2,
+    SizeRight = 3,
+    SizeBottomRight = 4,
+    SizeBottom = 5,
+    SizeBottomLeft = 6,
+    SizeLeft = 7,
+    Move = 8,
+    SizeKeyboard = 9,
+    MoveKeyboard = 10,
+    Cancel = 11,
+}
+
+impl MoveResizeDirection {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        use MoveResizeDirection::*;
+
+        let direction = match value {
+            0 => SizeTopLeft,
+            1 => SizeTop,
+            2 => SizeTopRight,
+            3 => SizeRight,
+            4 => SizeBottomRight,
+            5 => SizeBottom,
+            6 => SizeBottomLeft,
+            7 => SizeLeft,
+            8 => Move,
+            9 => SizeKeyboard,
+            10 => MoveKeyboard,
+            11 => Cancel,
+            _ => return None,
+        };
+        Some(direction)
+    }
+}
+
+/// Kind of [ClientRequest], used to subscribe to the specific requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientRequestKind {
+    Activate = 1,
+    Close = 2,
+    State = 3,
+    MoveResize = 4,
+    InteractiveMoveResize = 5,
+}
+
+impl ClientRequestKind {
+    fn from_u8(value: u8) -> Option<Self> {
+        use ClientRequestKind::*;
+
+        let kind = match value {
+            1 => Activate,
+            2 => Close,
+            3 => State,
+            4 => MoveResize,
+            5 => InteractiveMoveResize,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+impl Encode for ClientRequestKind {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        (*self as u8).encode_to(buffer)
+    }
+
+    fn encoded_size(&self) -> usize {
+        1
+    }
+}
+
+impl Decode for ClientRequestKind {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        buffer
+            .first()
+            .copied()
+            .and_then(Self::from_u8)
+            .ok_or(DecodeError::BadFormat)
+    }
+}
+
+/// Request sent to the root window by a client, pager or other tool via EWMH client message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientRequest {
+    /// `_NET_ACTIVE_WINDOW`
+    Activate {
+        window: WindowId,
+        source: RequestSource,
+    },
+    /// `_NET_CLOSE_WINDOW`
+    Close { window: WindowId },
+    /// `_NET_WM_STATE`
+    State {
+        window: WindowId,
+        action: StateAction,
+        first: WindowState,
+        second: Option<WindowState>,
+    },
+    /// `_NET_MOVERESIZE_WINDOW`. Fields that are `None` should be left unchanged.
+    MoveResize {
+        window: WindowId,
+        x: Option<i16>,
+        y: Option<i16>,
+        width: Option<u16>,
+        height: Option<u16>,
+    },
+    /// `_NET_WM_MOVERESIZE`
+    InteractiveMoveResize {
+        window: WindowId,
+        x_root: i16,
+        y_root: i16,
+        direction: MoveResizeDirection,
+        button: u8,
+    },
+}
+
+impl ClientRequest {
+    pub fn kind(&self) -> ClientRequestKind {
+        match self {
+            Self::Activate { .. } => ClientRequestKind::Activate,
+            Self::Close { .. } => ClientRequestKind::Close,
+            Self::State { .. } => ClientRequestKind::State,
+            Self::MoveResize { .. } => ClientRequestKind::MoveResize,
+            Self::InteractiveMoveResize { .. } => ClientRequestKind::InteractiveMoveResize,
+        }
+    }
+
+    pub fn window(&self) -> WindowId {
+        match *self {
+            Self::Activate { window, .. }
+            | Self::Close { window }
+            | Self::State { window, .. }
+            | Self::MoveResize { window, .. }
+            | Self::InteractiveMoveResize { window, .. } => window,
+        }
+    }
+}
+
+impl ClientRequestKind {
+    fn payload_size(&self) -> usize {
+        match self {
+            Self::Activate => 1,
+            Self::Close => 0,
+            Self::State => 3,
+            Self::MoveResize => 9,
+            Self::InteractiveMoveResize => 6,
+        }
+    }
+}
+
+impl Encode for ClientRequest {
+    type Error = EncodeError;
+
+    fn encode_to(&self, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        if buffer.len() < self.encoded_size() {
+            return Err(EncodeError::BufferSize);
+        }
+
+        self.kind().encode_to(&mut buffer[0..])?;
+        self.window().encode_to(&mut buffer[1..])?;
+        let payload = &mut buffer[5..];
+
+        match *self {
+            Self::Activate { source, .. } => (source as u8).encode_to(payload),
+            Self::Close { .. } => Ok(()),
+            Self::State {
+                action,
+                first,
+                second,
+                ..
+            } => {
+                payload[0] = action as u8;
+                payload[1] = first as u8;
+                payload[2] = second.map(|state| state as u8).unwrap_or(0);
+                Ok(())
+            }
+            Self::MoveResize {
+                x,
+                y,
+                width,
+                height,
+                ..
+            } => {
+                payload[0] = x.is_some() as u8
+                    | (y.is_some() as u8) << 1
+                    | (width.is_some() as u8) << 2
+                    | (height.is_some() as u8) << 3;
+                x.unwrap_or(0).encode_to(&mut payload[1..])?;
+                y.unwrap_or(0).encode_to(&mut payload[3..])?;
+                width.unwrap_or(0).encode_to(&mut payload[5..])?;
+                height.unwrap_or(0).encode_to(&mut payload[7..])
+            }
+            Self::InteractiveMoveResize {
+                x_root,
+                y_root,
+                direction,
+                button,
+                ..
+            } => {
+                x_root.encode_to(&mut payload[0..])?;
+                y_root.encode_to(&mut payload[2..])?;
+                payload[4] = direction as u8;
+                payload[5] = button;
+                Ok(())
+            }
+        }
+    }
+
+    fn encoded_size(&self) -> usize {
+        5 + self.kind().payload_size()
+    }
+}
+
+impl Decode for ClientRequest {
+    type Error = DecodeError;
+
+    fn decode(buffer: &[u8]) -> Result<Self, Self::Error> {
+        if buffer.len() < 5 {
+            return Err(DecodeError::BadFormat);
+        }
+
+        let kind = ClientRequestKind::decode(&buffer[0..])?;
+        if buffer.len() < 5 + kind.payload_size() {
+            return Err(DecodeError::BadFormat);
+        }
+        let window = WindowId::decode(&buffer[1..])?;
+        let payload = &buffer[5..];
+
+        let request = match kind {
+            ClientRequestKind::Activate => {
+                let source = match u8::decode(payload)? {
+                    1 => RequestSource::Application,
+                    2 => RequestSource::Pager,
+                    _ => RequestSource::Unknown,
+                };
+                Self::Activate { window, source }
+            }
+            ClientRequestKind::Close => Self::Close { window },
+            ClientRequestKind::State => {
+                let action = match payload[0] {
+                    0 => StateAction::Remove,
+                    1 => StateAction::Add,
+                    2 => StateAction::Toggle,
+                    _ => return Err(DecodeError::BadFormat),
+                };
+                Self::State {
+                    window,
+                    action,
+                    first: WindowState::from_u8(payload[1]).ok_or(DecodeError::BadFormat)?,
+                    second: WindowState::from_u8(payload[2]),
+                }
+            }
+            ClientRequestKind::MoveResize => {
+                let flags = u8::decode(payload)?;
+                let field = |bit: u8| flags & (1 << bit) != 0;
+                Self::MoveResize {
+                    window,
+                    x: Some(i16::decode(&payload[1..])?).filter(|_| field(0)),
+                    y: Some(i16::decode(&payload[3..])?).filter(|_| field(1)),
+                    width: Some(u16::decode(&payload[5..])?).filter(|_| field(2)),
+                    height: Some(u16::decode(&payload[7..])?).filter(|_| field(3)),
+                }
+            }
+            ClientRequestKind::InteractiveMoveResize => Self::InteractiveMoveResize {
+                window,
+                x_root: i16::decode(&payload[0..])?,
+                y_root: i16::decode(&payload[2..])?,
+                direction: MoveResizeDirection::from_u32(payload[4] as u32)
+                    .ok_or(DecodeError::BadFormat)?,
+                button: payload[5],
+            },
+        };
+
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SizeHints;
+
+    #[test]
+    fn constrain_without_hints() {
+        let hints = SizeHints::default();
+        assert_eq!(hints.constrain(300, 200), (300, 200));
+        assert_eq!(hints.constrain(0, 0), (1, 1));
+    }
+
+    #[test]
+    fn constrain_min_max() {
+        let hints = SizeHints {
+            min_size: Some((100, 50)),
+            max_size: Some((400, 300)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(200, 100), (200, 100));
+        assert_eq!(hints.constrain(50, 20), (100, 50));
+        assert_eq!(hints.constrain(500, 500), (400, 300));
+
+        // Zero maximum does not limit the size
+        let hints = SizeHints {
+            max_size: Some((0, 300)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(5000, 500), (5000, 300));
+    }
+
+    #[test]
+    fn constrain_increment() {
+        let hints = SizeHints {
+            base_size: Some((10, 20)),
+            increment: Some((8, 16)),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(100, 100), (98, 100));
+        assert_eq!(hints.constrain(106, 135), (106, 132));
+        // Base size is the minimum size if no minimum is set
+        assert_eq!(hints.constrain(5, 5), (10, 20));
+    }
+
+    #[test]
+    fn constrain_aspect() {
+        let hints = SizeHints {
+            aspect: Some(((1, 1), (2, 1))),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(150, 100), (150, 100));
+        assert_eq!(hints.constrain(100, 200), (100, 100));
+        assert_eq!(hints.constrain(400, 100), (200, 100));
+
+        let hints = SizeHints {
+            aspect: Some(((16, 9), (16, 9))),
+            ..SizeHints::default()
+        };
+        assert_eq!(hints.constrain(1600, 1000), (1600, 900));
+    }
+}
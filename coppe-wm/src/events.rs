@@ -1,74 +1,308 @@
 use coppe_common::{
+    button::{Button, Pointer},
     event::{Event, SubscriptionEvent},
-    key::Key,
-    window::{Geometry, Window},
+    key::{Key, ModMask},
+    window::{Geometry, Window, WindowType, WorkspaceId, ALL_WORKSPACES},
 };
+use x11rb::protocol::xproto::{self, ButtonPressEvent, Timestamp};
 use x11rb::protocol::Event as XEvent;
 
+use crate::wm::{Client, State};
+use crate::x11::X11Info;
+
 pub use coppe_common::event::{Subscription, SubscriptionFilter};
 
 #[derive(Debug, Clone)]
-pub struct WmEvent(Event);
+pub struct WmEvent {
+    event: Event,
+    /// Set for events of a key press that froze the keyboard.
+    frozen: Option<FrozenKey>,
+    /// Window the event concerns, captured when the event happened, so that filters are not
+    /// matched against later state.
+    window: Option<EventWindow>,
+}
+
+#[derive(Debug, Clone)]
+struct EventWindow {
+    id: xproto::Window,
+    /// Metadata of the client, `None` if the window is not managed.
+    client: Option<ClientInfo>,
+}
+
+/// Client metadata checked by subscription filters.
+#[derive(Debug, Clone)]
+struct ClientInfo {
+    wm_class: Option<(String, String)>,
+    title: Option<String>,
+    window_type: WindowType,
+    monitor: Option<u32>,
+    workspace: WorkspaceId,
+}
+
+impl ClientInfo {
+    fn new(state: &State, client: &Client) -> Self {
+        Self {
+            wm_class: client.wm_class.clone(),
+            title: client.title().map(ToString::to_string),
+            window_type: client.window_type(),
+            monitor: state.monitor_of(client).map(|(index, _)| index as u32),
+            workspace: client.workspace,
+        }
+    }
+}
+
+/// Key press that froze the keyboard with a synchronous grab. It is dispatched as several events,
+/// the keyboard is released after the last one.
+#[derive(Debug, Clone, Copy)]
+pub struct FrozenKey {
+    pub time: Timestamp,
+    /// Whether this is the last event of the key press.
+    pub last: bool,
+}
 
 impl WmEvent {
-    pub fn try_from(x_event: &XEvent) -> Option<Self> {
-        match x_event {
-            XEvent::KeyPress(event) => Some(
-                Event::KeyPress(Key {
-                    modmask: event.state.into(),
-                    keycode: event.detail.into(),
-                })
-                .into(),
-            ),
-            XEvent::KeyRelease(event) => Some(
-                Event::KeyRelease(Key {
-                    modmask: event.state.into(),
-                    keycode: event.detail.into(),
-                })
-                .into(),
-            ),
-            XEvent::MapRequest(event) => Some(Event::WindowAdd(event.window).into()),
-            XEvent::UnmapNotify(event) => Some(Event::WindowRemove(event.window).into()),
-            XEvent::ConfigureNotify(event) => Some(
-                Event::WindowConfigure(Window {
-                    id: event.window,
-                    geometry: Geometry {
-                        x: event.x,
-                        y: event.y,
-                        width: event.width,
-                        height: event.height,
-                    },
-                })
-                .into(),
-            ),
-            _ => None,
+    pub fn new(event: Event, state: &State) -> Self {
+        let window = event_window(&event, state).map(|id| EventWindow {
+            id,
+            client: state
+                .clients
+                .get(&id)
+                .map(|client| ClientInfo::new(state, client)),
+        });
+        Self {
+            event,
+            frozen: None,
+            window,
+        }
+    }
+
+    pub fn with_frozen_key(event: Event, frozen: FrozenKey, state: &State) -> Self {
+        Self {
+            frozen: Some(frozen),
+            ..Self::new(event, state)
         }
     }
 
+    pub fn frozen_key(&self) -> Option<FrozenKey> {
+        self.frozen
+    }
+
+    pub fn try_from(x11: &X11Info, state: &State, x_event: &XEvent) -> Option<Self> {
+        let event = match x_event {
+            XEvent::KeyPress(event) => Event::KeyPress(Key {
+                modmask: event.state.into(),
+                keycode: event.detail.into(),
+            }),
+            XEvent::KeyRelease(event) => Event::KeyRelease(Key {
+                modmask: event.state.into(),
+                keycode: event.detail.into(),
+            }),
+            // Client windows only report button presses of the click-to-focus grab
+            XEvent::ButtonPress(event) if event.event != event.root => {
+                Event::WindowClicked(button(event), pointer(event))
+            }
+            XEvent::ButtonPress(event) => Event::ButtonPress(button(event), pointer(event)),
+            XEvent::ButtonRelease(event) => Event::ButtonRelease(button(event), pointer(event)),
+            XEvent::MotionNotify(event) => Event::PointerMotion(Pointer {
+                root: event.root,
+                window: event.event,
+                child: (event.child != x11rb::NONE).then_some(event.child),
+                root_x: event.root_x,
+                root_y: event.root_y,
+                x: event.event_x,
+                y: event.event_y,
+                state: event.state.into(),
+            }),
+            XEvent::ConfigureNotify(event) => Event::WindowConfigure(Window {
+                id: event.window,
+                screen: x11.screen_of(event.event)?,
+                geometry: Geometry {
+                    x: event.x,
+                    y: event.y,
+                    width: event.width,
+                    height: event.height,
+                },
+            }),
+            _ => return None,
+        };
+        Some(Self::new(event, state))
+    }
+
     pub fn id(&self) -> u32 {
-        self.0.id()
+        self.event.id()
+    }
+
+    /// Check whether the event passes all filters of the group.
+    pub fn matches(&self, filters: &[SubscriptionFilter]) -> bool {
+        filters.iter().all(|filter| self.matches_filter(filter))
     }
 
-    pub fn matches(&self, _filters: &[SubscriptionFilter]) -> bool {
-        // No filters implemented yet
-        true
+    fn matches_filter(&self, filter: &SubscriptionFilter) -> bool {
+        let client = self
+            .window
+            .as_ref()
+            .and_then(|window| window.client.as_ref());
+
+        match filter {
+            SubscriptionFilter::Window(id) => {
+                self.window.as_ref().map(|window| window.id) == Some(*id)
+            }
+            SubscriptionFilter::Class(class) => client
+                .and_then(|client| client.wm_class.as_ref())
+                .is_some_and(|(_, value)| value == class),
+            SubscriptionFilter::Instance(instance) => client
+                .and_then(|client| client.wm_class.as_ref())
+                .is_some_and(|(value, _)| value == instance),
+            SubscriptionFilter::Title(pattern) => client
+                .and_then(|client| client.title.as_deref())
+                .is_some_and(|title| glob_match(pattern, title)),
+            SubscriptionFilter::WindowType(window_type) => {
+                client.is_some_and(|client| client.window_type == *window_type)
+            }
+            SubscriptionFilter::Monitor(index) => {
+                client.and_then(|client| client.monitor) == Some(*index)
+            }
+            SubscriptionFilter::Workspace(workspace) => match self.event {
+                Event::WorkspaceChange { current, .. } => current == *workspace,
+                _ => client.is_some_and(|client| {
+                    client.workspace == *workspace || client.workspace == ALL_WORKSPACES
+                }),
+            },
+        }
+    }
+}
+
+/// Window the event concerns. Key events concern the focused window, pointer events concern
+/// the top-level window under the pointer.
+fn event_window(event: &Event, state: &State) -> Option<xproto::Window> {
+    match event {
+        Event::KeyPress(_)
+        | Event::KeyRelease(_)
+        | Event::KeySymPress(..)
+        | Event::GrabbedKeyPress(_)
+        | Event::GrabbedKeyRelease(_) => state.focused,
+        Event::WindowAdd(window, _)
+        | Event::WindowRemove(window, _)
+        | Event::WindowTitleChanged(window)
+        | Event::PointerEnter(window)
+        | Event::PointerLeave(window)
+        | Event::WindowUrgencyChanged { window, .. }
+        | Event::WindowPropertyChanged { window, .. } => Some(*window),
+        Event::WindowConfigure(window) => Some(window.id),
+        Event::ClientRequest(request) => Some(request.window()),
+        Event::WindowFocus { current, .. } => *current,
+        Event::WindowClicked(_, pointer) => Some(pointer.window),
+        Event::ButtonPress(_, pointer)
+        | Event::ButtonRelease(_, pointer)
+        | Event::PointerMotion(pointer) => pointer.child,
+        Event::WorkspaceChange { .. } | Event::MonitorsChanged { .. } | Event::KeyboardGrabEnd => {
+            None
+        }
     }
 }
 
-impl From<Event> for WmEvent {
-    fn from(event: Event) -> Self {
-        Self(event)
+/// Match the text against a glob pattern, where `*` matches any sequence of characters and `?`
+/// matches a single character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Pattern position after the last `*` and the text position it is matched up to
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` consume one more character
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, t));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Button with keyboard modifiers only, so that it matches button subscriptions regardless of
+/// other buttons being held.
+fn button(event: &ButtonPressEvent) -> Button {
+    let modifiers = event.state & 0xff;
+    Button::new(ModMask::from(modifiers), event.detail.into())
+}
+
+fn pointer(event: &ButtonPressEvent) -> Pointer {
+    Pointer {
+        root: event.root,
+        window: event.event,
+        child: (event.child != x11rb::NONE).then_some(event.child),
+        root_x: event.root_x,
+        root_y: event.root_y,
+        x: event.event_x,
+        y: event.event_y,
+        state: event.state.into(),
     }
 }
 
 impl From<WmEvent> for Event {
     fn from(ev: WmEvent) -> Self {
-        ev.0
+        ev.event
     }
 }
 
 impl From<&WmEvent> for SubscriptionEvent {
     fn from(event: &WmEvent) -> Self {
-        SubscriptionEvent::from(&event.0)
+        SubscriptionEvent::from(&event.event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn glob_literal() {
+        assert!(glob_match("Firefox", "Firefox"));
+        assert!(!glob_match("Firefox", "firefox"));
+        assert!(!glob_match("Firefox", "Firefox "));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn glob_question_mark() {
+        assert!(glob_match("t?st", "test"));
+        assert!(glob_match("t?st", "täst"));
+        assert!(!glob_match("t?st", "tst"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn glob_star() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("* - Mozilla Firefox", "Page - Mozilla Firefox"));
+        assert!(glob_match("vim*", "vim"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("**a", "bba"));
+        assert!(!glob_match("a*b", "aXbY"));
+        assert!(!glob_match("*.rs", "main.rc"));
+    }
+
+    #[test]
+    fn glob_backtracking() {
+        assert!(glob_match("*ab", "aab"));
+        assert!(glob_match("*a?c", "abacabc"));
+        assert!(!glob_match("*aa", "aba"));
     }
 }
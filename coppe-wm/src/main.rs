@@ -13,13 +13,16 @@ fn main() {
     let (event_tx, event_rx) = std::sync::mpsc::channel();
     let x11 = X11Info::init().unwrap();
 
-    let mut wm = WindowManager::init(x11.clone(), event_tx).unwrap_or_else(|e| {
+    let mut wm = WindowManager::init(x11.clone(), event_tx.clone()).unwrap_or_else(|e| {
         println!("Error during wm initialization: {}", e);
         std::process::exit(1);
     });
-    let mut runner = Runner::init(x11, event_rx);
+    let mut runner = Runner::init(x11, wm.state(), event_tx, event_rx);
 
     std::thread::spawn(move || runner.run());
 
-    wm.run().unwrap();
+    if let Err(e) = wm.run() {
+        println!("Error while running wm: {}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,26 @@
+use log::*;
+use std::convert::TryFrom;
+
+use super::{ErrorCode, ValOrErrCode, XEnv};
+
+/// Enable or disable the built-in modifier+drag move and resize. Drags requested by clients and
+/// plugins are not affected.
+pub(super) fn drag_set_enabled(env: &XEnv, enabled: u32) -> i32 {
+    info!("{}: drag_set_enabled {}", env.id, enabled);
+
+    env.state
+        .write()
+        .set_drag_enabled(&env.x11, enabled != 0)
+        .map_err(Into::<ErrorCode>::into)
+        .value_or_error_code()
+}
+
+/// Set the distance in pixels at which moved windows snap to monitor edges. 0 disables snapping.
+pub(super) fn drag_set_snap_distance(env: &XEnv, distance: u32) -> i32 {
+    info!("{}: drag_set_snap_distance {}", env.id, distance);
+
+    u16::try_from(distance)
+        .map_err(|_| ErrorCode::BadArgument)
+        .map(|distance| env.state.write().drag_config.snap_distance = distance)
+        .value_or_error_code()
+}
@@ -0,0 +1,22 @@
+use coppe_common::window::FocusPolicy;
+use log::*;
+
+use super::{ErrorCode, ValOrErrCode, XEnv};
+
+/// Change the built-in focus policy. See [FocusPolicy] for available values.
+pub(super) fn focus_set_policy(env: &XEnv, policy: u32) -> i32 {
+    info!("{}: focus_set_policy {}", env.id, policy);
+
+    FocusPolicy::from_u32(policy)
+        .ok_or(ErrorCode::BadArgument)
+        .map(|policy| env.state.write().focus_policy = policy)
+        .value_or_error_code()
+}
+
+/// Enable or disable focusing and raising clicked windows with [FocusPolicy::Click]. Plugins
+/// still receive `WindowClicked` events and may handle clicks themselves.
+pub(super) fn focus_set_click_enabled(env: &XEnv, enabled: u32) {
+    info!("{}: focus_set_click_enabled {}", env.id, enabled);
+
+    env.state.write().click_config.focus = enabled != 0;
+}
@@ -0,0 +1,50 @@
+use log::*;
+use std::time::Duration;
+
+use super::{ErrorCode, SubEnv, ValOrErrCode};
+use crate::wm::DEFAULT_GRAB_TIMEOUT;
+
+/// Grab the keyboard, so that all key presses and releases are delivered to the plugin as
+/// `GrabbedKeyPress` and `GrabbedKeyRelease` events, regardless of subscriptions.
+///
+/// The grab is released by the WM with `KeyboardGrabEnd` event when Escape is pressed or when no
+/// key is pressed for `timeout_ms` milliseconds. 0 uses the default timeout, timeouts are capped
+/// at one minute. Returns 0 on success or [ErrorCode::Access] if the keyboard is grabbed by
+/// another plugin or X client.
+pub(super) fn keyboard_grab(env: &SubEnv, timeout_ms: u32) -> i32 {
+    info!("{}: keyboard_grab {}", env.id, timeout_ms);
+
+    let timeout = match timeout_ms {
+        0 => DEFAULT_GRAB_TIMEOUT,
+        ms => Duration::from_millis(u64::from(ms)),
+    };
+    grab_keyboard(env, timeout).value_or_error_code()
+}
+
+/// Release the keyboard grab. Returns 0 on success or [ErrorCode::BadArgument] if the plugin
+/// does not hold the grab.
+pub(super) fn keyboard_ungrab(env: &SubEnv) -> i32 {
+    info!("{}: keyboard_ungrab", env.id);
+
+    ungrab_keyboard(env).value_or_error_code()
+}
+
+fn grab_keyboard(env: &SubEnv, timeout: Duration) -> Result<(), ErrorCode> {
+    if env
+        .subscriptions
+        .write()
+        .grab_keyboard(env.id.clone(), timeout)?
+    {
+        Ok(())
+    } else {
+        Err(ErrorCode::Access)
+    }
+}
+
+fn ungrab_keyboard(env: &SubEnv) -> Result<(), ErrorCode> {
+    if env.subscriptions.write().ungrab_keyboard(&env.id)? {
+        Ok(())
+    } else {
+        Err(ErrorCode::BadArgument)
+    }
+}
@@ -6,24 +6,32 @@ use log::*;
 use parking_lot::{Mutex, RwLock};
 use std::{
     collections::{HashMap, VecDeque},
-    sync::Arc,
+    sync::{atomic::AtomicBool, atomic::Ordering, mpsc, Arc},
 };
 use wasmer::{
     imports, Array, Function, ImportObject, LazyInit, Memory, Store, ValueType, WasmPtr, WasmerEnv,
 };
 use x11rb::errors::{ConnectionError as X11ConnectionError, ReplyError as X11ReplyError};
 
+mod drag;
+mod focus;
+mod keyboard;
+mod monitor;
 mod window;
+mod workspace;
 
 use super::plug_mgr::PluginId;
 use super::sub_mgr::SubscriptionManager;
-use crate::events::Subscription;
+use crate::events::{Subscription, WmEvent};
+use crate::wm::SharedState;
 use crate::x11::X11Info;
 
 #[derive(WasmerEnv, Clone)]
 struct XEnv {
     id: PluginId,
     x11: X11Info,
+    state: SharedState,
+    tx: mpsc::Sender<WmEvent>,
     #[wasmer(export)]
     memory: LazyInit<Memory>,
 }
@@ -40,20 +48,27 @@ struct SubEnv {
 struct EventEnv {
     id: PluginId,
     events: Arc<RwLock<HashMap<PluginId, Mutex<VecDeque<Event>>>>>,
+    replay: Arc<AtomicBool>,
     #[wasmer(export)]
     memory: LazyInit<Memory>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) fn import_objects(
     plugin_id: PluginId,
     store: &Store,
     x11: X11Info,
+    state: SharedState,
+    tx: mpsc::Sender<WmEvent>,
     subscriptions: Arc<RwLock<SubscriptionManager>>,
     events: Arc<RwLock<HashMap<PluginId, Mutex<VecDeque<Event>>>>>,
+    replay: Arc<AtomicBool>,
 ) -> ImportObject {
     let cmd_env = XEnv {
         id: plugin_id.clone(),
         x11,
+        state,
+        tx,
         memory: Default::default(),
     };
     let sub_env = SubEnv {
@@ -64,22 +79,48 @@ pub(super) fn import_objects(
     let event_env = EventEnv {
         id: plugin_id,
         events,
+        replay,
         memory: Default::default(),
     };
 
     imports! {
         "env" => {
             "subscribe" => Function::new_native_with_env(store, sub_env.clone(), subscribe),
-            "unsubscribe" => Function::new_native_with_env(store, sub_env, unsubscribe),
+            "unsubscribe" => Function::new_native_with_env(store, sub_env.clone(), unsubscribe),
+            "keyboard_grab" => Function::new_native_with_env(store, sub_env.clone(), keyboard::keyboard_grab),
+            "keyboard_ungrab" => Function::new_native_with_env(store, sub_env, keyboard::keyboard_ungrab),
             "event_read" => Function::new_native_with_env(store, event_env.clone(), event_read),
-            "event_len" => Function::new_native_with_env(store, event_env, event_len),
+            "event_len" => Function::new_native_with_env(store, event_env.clone(), event_len),
+            "event_replay" => Function::new_native_with_env(store, event_env, event_replay),
             "debug_log" => Function::new_native_with_env(store, cmd_env.clone(), debug_log),
             "window_move" => Function::new_native_with_env(store, cmd_env.clone(), window::window_move),
             "window_resize" => Function::new_native_with_env(store, cmd_env.clone(), window::window_resize),
             "window_move_resize" => Function::new_native_with_env(store, cmd_env.clone(), window::window_move_resize),
+            "window_resize_with_hints" => Function::new_native_with_env(store, cmd_env.clone(), window::window_resize_with_hints),
+            "window_move_resize_with_hints" => Function::new_native_with_env(store, cmd_env.clone(), window::window_move_resize_with_hints),
             "window_focus" => Function::new_native_with_env(store, cmd_env.clone(), window::window_focus),
+            "window_raise" => Function::new_native_with_env(store, cmd_env.clone(), window::window_raise),
+            "window_begin_move" => Function::new_native_with_env(store, cmd_env.clone(), window::window_begin_move),
+            "window_begin_resize" => Function::new_native_with_env(store, cmd_env.clone(), window::window_begin_resize),
             "window_get_properties" => Function::new_native_with_env(store, cmd_env.clone(), window::window_get_properties),
+            "window_get_size_hints" => Function::new_native_with_env(store, cmd_env.clone(), window::window_get_size_hints),
+            "window_get_title" => Function::new_native_with_env(store, cmd_env.clone(), window::window_get_title),
+            "window_get_class" => Function::new_native_with_env(store, cmd_env.clone(), window::window_get_class),
+            "window_get_role" => Function::new_native_with_env(store, cmd_env.clone(), window::window_get_role),
+            "window_get_pid" => Function::new_native_with_env(store, cmd_env.clone(), window::window_get_pid),
+            "window_get_type" => Function::new_native_with_env(store, cmd_env.clone(), window::window_get_type),
+            "window_list" => Function::new_native_with_env(store, cmd_env.clone(), window::window_list),
+            "window_focused" => Function::new_native_with_env(store, cmd_env.clone(), window::window_focused),
             "window_close" => Function::new_native_with_env(store, cmd_env.clone(), window::window_close),
+            "window_move_to_workspace" => Function::new_native_with_env(store, cmd_env.clone(), window::window_move_to_workspace),
+            "workspace_switch" => Function::new_native_with_env(store, cmd_env.clone(), workspace::workspace_switch),
+            "workspace_current" => Function::new_native_with_env(store, cmd_env.clone(), workspace::workspace_current),
+            "workspace_set_names" => Function::new_native_with_env(store, cmd_env.clone(), workspace::workspace_set_names),
+            "focus_set_policy" => Function::new_native_with_env(store, cmd_env.clone(), focus::focus_set_policy),
+            "focus_set_click_enabled" => Function::new_native_with_env(store, cmd_env.clone(), focus::focus_set_click_enabled),
+            "drag_set_enabled" => Function::new_native_with_env(store, cmd_env.clone(), drag::drag_set_enabled),
+            "drag_set_snap_distance" => Function::new_native_with_env(store, cmd_env.clone(), drag::drag_set_snap_distance),
+            "monitors_list" => Function::new_native_with_env(store, cmd_env.clone(), monitor::monitors_list),
             "spawn" => Function::new_native_with_env(store, cmd_env, spawn),
         }
     }
@@ -118,9 +159,14 @@ unsafe fn write_to_ptr<T: ValueType>(
 /// Expects a pointer to a buffer, describing event and it's optional filters.
 ///
 /// Event description buffer has the following format:
-/// * `<event_id: [byte; 4]>` - see [events::id](crate::events::id);
+/// * `<event_id: [byte; 4]>` - see [events::id](crate::events::id), upper 16 bits hold flags, see
+///   [subscription_flag](coppe_common::event::subscription_flag);
 /// * `<event_payload: byte array>` - size and contents depend on `event_id`, see [EncodedEvent];
 /// * `[event_filter: <event_filter_id: [byte; 4]>, <event_filter_payload: byte array>]`;
+///
+/// Returns 0 on success or error code. Key and button bindings fail with [ErrorCode::Access] if
+/// another X client holds the grab, and return [SUBSCRIBED_WITH_CONFLICT] if the subscription was
+/// added, but another plugin is bound to the same key or button with overlapping modifiers.
 fn subscribe(env: &SubEnv, event_ptr: WasmPtr<u8, Array>, event_len: u32) -> i32 {
     env.memory_ref()
         .ok_or(ErrorCode::UnableToGetMemory)
@@ -131,8 +177,11 @@ fn subscribe(env: &SubEnv, event_ptr: WasmPtr<u8, Array>, event_len: u32) -> i32
             let event: Vec<u8> = event.iter().map(|cell| cell.get()).collect();
             let sub = Subscription::decode(event.as_ref()).map_err(|_| ErrorCode::BadArgument)?;
             info!("{}: subscribe to {:?}", env.id, sub);
-            env.subscriptions.write().subscribe(env.id.clone(), sub);
-            Ok(())
+            if env.subscriptions.write().subscribe(env.id.clone(), sub)? {
+                Ok(SUBSCRIBED_WITH_CONFLICT)
+            } else {
+                Ok(ErrorCode::Ok as u32)
+            }
         })
         .value_or_error_code()
 }
@@ -143,7 +192,8 @@ fn subscribe(env: &SubEnv, event_ptr: WasmPtr<u8, Array>, event_len: u32) -> i32
 /// passed, subscription for all matching events will be cancelled. Returns 0 on success or error code.
 ///
 /// Event description buffer has the following format:
-/// * `<event_id: [byte; 4]>` - see [events::id](crate::events::id);
+/// * `<event_id: [byte; 4]>` - see [events::id](crate::events::id), upper 16 bits hold flags,
+///   which are ignored;
 /// * `<event_payload: byte array>` - size and fields expected depend on `event_id`, see [EncodedEvent];
 /// * `[event_filter: <event_filter_id: [byte; 4]>, <event_filter_payload: byte array>]`;
 fn unsubscribe(env: &SubEnv, event_ptr: WasmPtr<u8, Array>, event_len: u32) -> i32 {
@@ -156,7 +206,7 @@ fn unsubscribe(env: &SubEnv, event_ptr: WasmPtr<u8, Array>, event_len: u32) -> i
             let event: Vec<u8> = event.iter().map(|cell| cell.get()).collect();
             let sub = Subscription::decode(event.as_ref()).map_err(|_| ErrorCode::BadArgument)?;
             info!("{}: unsubscribe from {:?}", env.id, sub);
-            env.subscriptions.write().unsubscribe(&env.id, &sub);
+            env.subscriptions.write().unsubscribe(&env.id, &sub)?;
             Ok(())
         })
         .value_or_error_code()
@@ -222,6 +272,14 @@ fn event_len(env: &EventEnv) -> u32 {
         .unwrap_or(0)
 }
 
+/// Replay the key press that is being handled to the focused client instead of consuming it.
+/// Only has effect for conditional key bindings, the key press is replayed if none of the
+/// subscribed plugins consume it.
+fn event_replay(env: &EventEnv) {
+    info!("{}: event_replay", env.id);
+    env.replay.store(true, Ordering::Relaxed);
+}
+
 /// Print debug message to logs. Returns 0 on success or error code.
 fn debug_log(env: &XEnv, cmd_ptr: WasmPtr<u8, Array>, cmd_len: u32) -> i32 {
     env.memory_ref()
@@ -263,11 +321,17 @@ fn spawn(env: &XEnv, cmd_ptr: WasmPtr<u8, Array>, cmd_len: u32) -> i32 {
         .value_or_error_code()
 }
 
+/// Returned by `subscribe` when the subscription was added, but another plugin is bound to the
+/// same key or button. Both plugins receive the event.
+const SUBSCRIBED_WITH_CONFLICT: u32 = 1;
+
 /// Core API call error codes.
 #[derive(Debug)]
 enum ErrorCode {
     /// Plugin memory could not be accessed. Should never happen if plugin was initialized properly.
     UnableToGetMemory = -128,
+    /// Key or button is already grabbed by another X client.
+    Access = -5,
     /// Window with provided id does not exist.
     Window = -4,
     /// Invalid argument provided.
@@ -285,6 +349,7 @@ impl From<X11ReplyError> for ErrorCode {
         match e {
             X11ReplyError::ConnectionError(_) => ErrorCode::Unknown,
             X11ReplyError::X11Error(e) => match e.error_kind {
+                x11rb::protocol::ErrorKind::Access => ErrorCode::Access,
                 x11rb::protocol::ErrorKind::Match => ErrorCode::BadArgument,
                 x11rb::protocol::ErrorKind::Window => ErrorCode::Window,
                 _ => ErrorCode::Unknown,
@@ -0,0 +1,40 @@
+use coppe_common::encoding::EncodeExt;
+use log::*;
+use wasmer::{Array, WasmPtr};
+
+use super::{write_to_ptr, ErrorCode, ValOrErrCode, XEnv};
+
+/// Write encoded list of monitors of the given screen to the buffer. Returns number of written
+/// bytes or error code.
+///
+/// Buffer has the following format:
+/// * `<count: byte>`;
+/// * `count` times `<x: [byte; 2]>, <y: [byte; 2]>, <width: [byte; 2]>, <height: [byte; 2]>,
+///   <primary: byte>`.
+pub(super) fn monitors_list(
+    env: &XEnv,
+    screen: u32,
+    buf_ptr: WasmPtr<u8, Array>,
+    buf_len: u32,
+) -> i32 {
+    env.memory_ref()
+        .ok_or(ErrorCode::UnableToGetMemory)
+        .and_then(|memory| {
+            let monitors = env
+                .state
+                .read()
+                .monitors
+                .get(screen as usize)
+                .copied()
+                .ok_or(ErrorCode::BadArgument)?;
+            info!(
+                "{}: monitors_list {}; Response: {:?}",
+                env.id, screen, monitors
+            );
+
+            let encoded = monitors.encode_to_vec().map_err(|_| ErrorCode::Unknown)?;
+            let written = unsafe { write_to_ptr(&encoded, memory, buf_ptr, buf_len, 0)? };
+            Ok(written as u32)
+        })
+        .value_or_error_code()
+}
@@ -1,8 +1,14 @@
+use coppe_common::{
+    encoding::{Encode, EncodeExt},
+    window::{Geometry, MapState, MoveResizeDirection, Window},
+};
 use log::*;
-use wasmer::WasmPtr;
+use wasmer::{Array, WasmPtr};
 use x11rb::protocol::xproto::*;
+use x11rb::CURRENT_TIME;
 
-use super::{ErrorCode, ValOrErrCode, XEnv};
+use super::{write_to_ptr, ErrorCode, ValOrErrCode, XEnv};
+use crate::wm::{Client, WmState};
 
 pub(super) fn window_move(env: &XEnv, window_id: u32, x: i16, y: i16) -> i32 {
     info!("{}: window_move {} to [{}, {}]", env.id, window_id, x, y);
@@ -59,18 +65,175 @@ pub(super) fn window_move_resize(
         .value_or_error_code()
 }
 
+/// Resize a managed window, adjusting the size to the client's `WM_NORMAL_HINTS`.
+pub(super) fn window_resize_with_hints(env: &XEnv, window_id: u32, width: u16, height: u16) -> i32 {
+    info!(
+        "{}: window_resize_with_hints {} to [{}, {}]",
+        env.id, window_id, width, height
+    );
+
+    constrain_size(env, window_id, width, height)
+        .and_then(|(width, height)| {
+            let aux = ConfigureWindowAux::default()
+                .width(u32::from(width))
+                .height(u32::from(height));
+            env.x11.conn.configure_window(window_id, &aux)?.check()?;
+            Ok(())
+        })
+        .value_or_error_code()
+}
+
+/// Move and resize a managed window, adjusting the size to the client's `WM_NORMAL_HINTS`.
+pub(super) fn window_move_resize_with_hints(
+    env: &XEnv,
+    window_id: u32,
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+) -> i32 {
+    info!(
+        "{}: window_move_resize_with_hints {} to {{x:{},y:{},width:{},height:{}}}",
+        env.id, window_id, x, y, width, height
+    );
+
+    constrain_size(env, window_id, width, height)
+        .and_then(|(width, height)| {
+            let aux = ConfigureWindowAux::default()
+                .x(i32::from(x))
+                .y(i32::from(y))
+                .width(u32::from(width))
+                .height(u32::from(height));
+            env.x11.conn.configure_window(window_id, &aux)?.check()?;
+            Ok(())
+        })
+        .value_or_error_code()
+}
+
+fn constrain_size(
+    env: &XEnv,
+    window_id: u32,
+    width: u16,
+    height: u16,
+) -> Result<(u16, u16), ErrorCode> {
+    env.state
+        .read()
+        .clients
+        .get(&window_id)
+        .map(|client| client.size_hints.constrain(width, height))
+        .ok_or(ErrorCode::Window)
+}
+
+/// Write encoded `WM_NORMAL_HINTS` of a managed window to the buffer. Returns number of written
+/// bytes or error code.
+pub(super) fn window_get_size_hints(
+    env: &XEnv,
+    window_id: u32,
+    buf_ptr: WasmPtr<u8, Array>,
+    buf_len: u32,
+) -> i32 {
+    info!("{}: window_get_size_hints {}", env.id, window_id);
+    env.memory_ref()
+        .ok_or(ErrorCode::UnableToGetMemory)
+        .and_then(|memory| {
+            let hints = env
+                .state
+                .read()
+                .clients
+                .get(&window_id)
+                .map(|client| client.size_hints)
+                .ok_or(ErrorCode::Window)?;
+            let encoded = hints.encode_to_vec().map_err(|_| ErrorCode::Unknown)?;
+            let written = unsafe { write_to_ptr(&encoded, memory, buf_ptr, buf_len, 0)? };
+            Ok(written as u32)
+        })
+        .value_or_error_code()
+}
+
+/// Give input focus to the window without changing stacking order. Fails with
+/// [ErrorCode::Window] if the window is not a client mapped on the current workspace. Windows
+/// that do not accept input are left unfocused and fail with [ErrorCode::BadArgument].
 pub(super) fn window_focus(env: &XEnv, window_id: u32) -> i32 {
     info!("{}: window_focus {}", env.id, window_id);
-    let aux = ConfigureWindowAux::default().stack_mode(StackMode::ABOVE);
 
-    env.x11
-        .conn
-        .configure_window(window_id, &aux)
-        .map_err(Into::<ErrorCode>::into)
-        .and_then(|cookie| cookie.check().map_err(Into::into))
+    let mut state = env.state.write();
+    if !state.is_viewable(window_id) {
+        return ErrorCode::Window as i32;
+    }
+    state
+        .focus(&env.x11, window_id, CURRENT_TIME)
+        .map_err(Into::into)
+        .and_then(|focused| {
+            if focused {
+                Ok(())
+            } else {
+                Err(ErrorCode::BadArgument)
+            }
+        })
+        .value_or_error_code()
+}
+
+/// Place the window on top of the stack. Fails with [ErrorCode::Window] if the window is not a
+/// client mapped on the current workspace.
+pub(super) fn window_raise(env: &XEnv, window_id: u32) -> i32 {
+    info!("{}: window_raise {}", env.id, window_id);
+
+    env.state
+        .write()
+        .raise_window(&env.x11, window_id)
+        .map_err(Into::into)
+        .and_then(|raised| {
+            if raised {
+                Ok(())
+            } else {
+                Err(ErrorCode::Window)
+            }
+        })
         .value_or_error_code()
 }
 
+/// Start interactive move of the window, following the pointer until a button is released.
+pub(super) fn window_begin_move(env: &XEnv, window_id: u32) -> i32 {
+    info!("{}: window_begin_move {}", env.id, window_id);
+
+    begin_drag(env, window_id, MoveResizeDirection::Move).value_or_error_code()
+}
+
+/// Start interactive resize of the window. `direction` is one of the `Size*` values of
+/// [MoveResizeDirection] and selects the dragged edge or corner.
+pub(super) fn window_begin_resize(env: &XEnv, window_id: u32, direction: u32) -> i32 {
+    info!(
+        "{}: window_begin_resize {}, direction {}",
+        env.id, window_id, direction
+    );
+
+    match MoveResizeDirection::from_u32(direction) {
+        Some(direction) if (direction as u32) < MoveResizeDirection::Move as u32 => {
+            begin_drag(env, window_id, direction)
+        }
+        _ => Err(ErrorCode::BadArgument),
+    }
+    .value_or_error_code()
+}
+
+fn begin_drag(env: &XEnv, window_id: u32, direction: MoveResizeDirection) -> Result<(), ErrorCode> {
+    let mut state = env.state.write();
+    let root = state
+        .clients
+        .get(&window_id)
+        .and_then(|client| env.x11.screen(client.screen))
+        .map(|screen| screen.root)
+        .ok_or(ErrorCode::Window)?;
+
+    let pointer = env.x11.conn.query_pointer(root)?.reply()?;
+    let origin = (pointer.root_x, pointer.root_y);
+    if state.begin_drag(&env.x11, window_id, direction, origin, CURRENT_TIME)? {
+        Ok(())
+    } else {
+        Err(ErrorCode::Execution)
+    }
+}
+
 pub(super) fn window_get_properties(
     env: &XEnv,
     window_id: u32,
@@ -83,7 +246,24 @@ pub(super) fn window_get_properties(
     env.memory_ref()
         .ok_or(ErrorCode::UnableToGetMemory)
         .and_then(|memory| {
-            let geometry = env.x11.conn.get_geometry(window_id)?.reply()?;
+            let cached = env
+                .state
+                .read()
+                .clients
+                .get(&window_id)
+                .map(|client| client.geometry);
+            let geometry = match cached {
+                Some(geometry) => geometry,
+                None => {
+                    let reply = env.x11.conn.get_geometry(window_id)?.reply()?;
+                    Geometry {
+                        x: reply.x,
+                        y: reply.y,
+                        width: reply.width,
+                        height: reply.height,
+                    }
+                }
+            };
             unsafe {
                 x.deref_mut(memory)
                     .ok_or(ErrorCode::BadArgument)?
@@ -108,20 +288,173 @@ pub(super) fn window_get_properties(
 pub(super) fn window_close(env: &XEnv, window_id: u32) -> i32 {
     info!("{}: window_close {}", env.id, window_id);
 
-    let data = [env.x11.atoms.WM_DELETE_WINDOW, 0, 0, 0, 0];
-    let event = ClientMessageEvent {
-        response_type: CLIENT_MESSAGE_EVENT,
-        format: 32,
-        sequence: 0,
-        window: window_id,
-        type_: env.x11.atoms.WM_PROTOCOLS,
-        data: data.into(),
-    };
-
     env.x11
-        .conn
-        .send_event(false, window_id, EventMask::NO_EVENT, &event)
+        .send_protocol(window_id, env.x11.atoms.WM_DELETE_WINDOW, CURRENT_TIME)
         .map_err(Into::<ErrorCode>::into)
         .and_then(|cookie| cookie.check().map_err(Into::into))
         .value_or_error_code()
 }
+
+pub(super) fn window_move_to_workspace(env: &XEnv, window_id: u32, workspace: u32) -> i32 {
+    info!(
+        "{}: window_move_to_workspace {} to {}",
+        env.id, window_id, workspace
+    );
+
+    env.state
+        .write()
+        .move_to_workspace(&env.x11, window_id, workspace)
+        .map_err(Into::<ErrorCode>::into)
+        .and_then(|moved| moved.then_some(()).ok_or(ErrorCode::BadArgument))
+        .value_or_error_code()
+}
+
+/// Write window title to the buffer. Returns full title length in bytes, which may be larger than
+/// the buffer, or error code.
+pub(super) fn window_get_title(
+    env: &XEnv,
+    window_id: u32,
+    buf_ptr: WasmPtr<u8, Array>,
+    buf_len: u32,
+) -> i32 {
+    info!("{}: window_get_title {}", env.id, window_id);
+    write_client_value(env, window_id, buf_ptr, buf_len, |client| {
+        client.title().unwrap_or_default().as_bytes().to_vec()
+    })
+}
+
+/// Write `WM_CLASS` to the buffer as NUL-separated instance and class names. Returns full value
+/// length in bytes, which may be larger than the buffer, or error code.
+pub(super) fn window_get_class(
+    env: &XEnv,
+    window_id: u32,
+    buf_ptr: WasmPtr<u8, Array>,
+    buf_len: u32,
+) -> i32 {
+    info!("{}: window_get_class {}", env.id, window_id);
+    write_client_value(env, window_id, buf_ptr, buf_len, |client| {
+        match &client.wm_class {
+            Some((instance, class)) => [instance.as_bytes(), class.as_bytes()].join(&0),
+            None => Vec::new(),
+        }
+    })
+}
+
+/// Write `WM_WINDOW_ROLE` to the buffer. Returns full role length in bytes, which may be larger
+/// than the buffer, or error code.
+pub(super) fn window_get_role(
+    env: &XEnv,
+    window_id: u32,
+    buf_ptr: WasmPtr<u8, Array>,
+    buf_len: u32,
+) -> i32 {
+    info!("{}: window_get_role {}", env.id, window_id);
+    write_client_value(env, window_id, buf_ptr, buf_len, |client| {
+        client
+            .role
+            .as_deref()
+            .unwrap_or_default()
+            .as_bytes()
+            .to_vec()
+    })
+}
+
+/// Returns `_NET_WM_PID` of the window, 0 if it is not set, or error code.
+pub(super) fn window_get_pid(env: &XEnv, window_id: u32) -> i32 {
+    info!("{}: window_get_pid {}", env.id, window_id);
+    env.state
+        .read()
+        .clients
+        .get(&window_id)
+        .map(|client| client.pid.unwrap_or(0))
+        .ok_or(ErrorCode::Window)
+        .value_or_error_code()
+}
+
+/// Returns EWMH window type, see [WindowType](coppe_common::window::WindowType), or error code.
+pub(super) fn window_get_type(env: &XEnv, window_id: u32) -> i32 {
+    info!("{}: window_get_type {}", env.id, window_id);
+    env.state
+        .read()
+        .clients
+        .get(&window_id)
+        .map(|client| client.window_type() as u32)
+        .ok_or(ErrorCode::Window)
+        .value_or_error_code()
+}
+
+fn write_client_value<F>(
+    env: &XEnv,
+    window_id: u32,
+    buf_ptr: WasmPtr<u8, Array>,
+    buf_len: u32,
+    value: F,
+) -> i32
+where
+    F: FnOnce(&Client) -> Vec<u8>,
+{
+    env.memory_ref()
+        .ok_or(ErrorCode::UnableToGetMemory)
+        .and_then(|memory| {
+            let value = env
+                .state
+                .read()
+                .clients
+                .get(&window_id)
+                .map(value)
+                .ok_or(ErrorCode::Window)?;
+            unsafe { write_to_ptr(&value, memory, buf_ptr, buf_len, 0)? };
+            Ok(value.len() as u32)
+        })
+        .value_or_error_code()
+}
+
+/// Write the list of managed windows in order of mapping to the buffer. Returns full list size in
+/// bytes, which may be larger than the buffer, or error code.
+///
+/// Each window is encoded as:
+/// * `<id: [byte; 4]>, <screen: [byte; 4]>, <geometry: [byte; 8]>` - see [Window];
+/// * `<map_state: byte>` - see [MapState].
+pub(super) fn window_list(env: &XEnv, buf_ptr: WasmPtr<u8, Array>, buf_len: u32) -> i32 {
+    info!("{}: window_list", env.id);
+    env.memory_ref()
+        .ok_or(ErrorCode::UnableToGetMemory)
+        .and_then(|memory| {
+            let state = env.state.read();
+            let mut encoded = Vec::new();
+            for client in state
+                .client_list
+                .iter()
+                .filter_map(|win| state.clients.get(win))
+            {
+                let window = Window {
+                    id: client.id,
+                    screen: client.screen,
+                    geometry: client.geometry,
+                };
+                let map_state = if client.state == WmState::Iconic {
+                    MapState::Iconic
+                } else if client.is_visible(state.current_workspace) {
+                    MapState::Mapped
+                } else {
+                    MapState::Hidden
+                };
+
+                let offset = encoded.len();
+                encoded.resize(offset + window.encoded_size() + 1, 0);
+                window
+                    .encode_to(&mut encoded[offset..])
+                    .map_err(|_| ErrorCode::Unknown)?;
+                encoded[offset + window.encoded_size()] = map_state as u8;
+            }
+
+            unsafe { write_to_ptr(&encoded, memory, buf_ptr, buf_len, 0)? };
+            Ok(encoded.len() as u32)
+        })
+        .value_or_error_code()
+}
+
+/// Returns id of the focused window or 0 if no managed window is focused.
+pub(super) fn window_focused(env: &XEnv) -> u32 {
+    env.state.read().focused.unwrap_or(x11rb::NONE)
+}
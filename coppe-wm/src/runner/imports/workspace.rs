@@ -0,0 +1,61 @@
+use log::*;
+use wasmer::{Array, WasmPtr};
+
+use super::{ErrorCode, ValOrErrCode, XEnv};
+use crate::events::WmEvent;
+
+pub(super) fn workspace_switch(env: &XEnv, workspace: u32) -> i32 {
+    info!("{}: workspace_switch to {}", env.id, workspace);
+
+    let mut state = env.state.write();
+    if workspace as usize >= state.workspaces.len() {
+        return ErrorCode::BadArgument as i32;
+    }
+
+    state
+        .switch_workspace(&env.x11, workspace)
+        .map(|event| {
+            if let Some(event) = event {
+                env.tx.send(WmEvent::new(event, &state)).ok();
+            }
+        })
+        .map_err(Into::into)
+        .value_or_error_code()
+}
+
+/// Returns index of the current workspace.
+pub(super) fn workspace_current(env: &XEnv) -> u32 {
+    env.state.read().current_workspace
+}
+
+/// Replace the list of workspaces. Expects a buffer of NUL-separated UTF-8 workspace names.
+pub(super) fn workspace_set_names(
+    env: &XEnv,
+    names_ptr: WasmPtr<u8, Array>,
+    names_len: u32,
+) -> i32 {
+    env.memory_ref()
+        .ok_or(ErrorCode::UnableToGetMemory)
+        .and_then(|memory| {
+            let names = names_ptr
+                .get_utf8_string(memory, names_len)
+                .ok_or(ErrorCode::BadArgument)?;
+            info!("{}: workspace_set_names {:?}", env.id, names);
+
+            let names: Vec<String> = names
+                .split('\0')
+                .filter(|name| !name.is_empty())
+                .map(ToString::to_string)
+                .collect();
+            if names.is_empty() {
+                return Err(ErrorCode::BadArgument);
+            }
+
+            let mut state = env.state.write();
+            if let Some(event) = state.set_workspaces(&env.x11, names)? {
+                env.tx.send(WmEvent::new(event, &state)).ok();
+            }
+            Ok(())
+        })
+        .value_or_error_code()
+}
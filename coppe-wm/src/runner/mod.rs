@@ -6,6 +6,7 @@ mod plug_mgr;
 mod sub_mgr;
 
 use crate::events::WmEvent;
+use crate::wm::SharedState;
 use crate::x11::X11Info;
 use plug_mgr::PluginManager;
 
@@ -15,9 +16,14 @@ pub struct Runner {
 }
 
 impl Runner {
-    pub fn init(conn: X11Info, rx: mpsc::Receiver<WmEvent>) -> Self {
+    pub fn init(
+        conn: X11Info,
+        state: SharedState,
+        tx: mpsc::Sender<WmEvent>,
+        rx: mpsc::Receiver<WmEvent>,
+    ) -> Self {
         Self {
-            plugins: PluginManager::init(conn),
+            plugins: PluginManager::init(conn, state, tx),
             rx,
         }
     }
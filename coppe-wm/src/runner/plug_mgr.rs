@@ -7,13 +7,18 @@ use std::{
     fs::File,
     io::Read,
     path::PathBuf,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
 };
 use wasmer::{Instance, Module, NativeFunc, Store};
+use x11rb::protocol::xproto::Timestamp;
 
 use super::imports;
 use super::sub_mgr::SubscriptionManager;
 use crate::events::WmEvent;
+use crate::wm::SharedState;
 use crate::x11::X11Info;
 
 pub struct PluginManager {
@@ -21,17 +26,31 @@ pub struct PluginManager {
     instances: HashMap<PluginId, Instance>,
     events: Arc<RwLock<HashMap<PluginId, Mutex<VecDeque<Event>>>>>,
     subscriptions: Arc<RwLock<SubscriptionManager>>,
+    /// Set by a plugin from `handle` to replay the frozen key press instead of consuming it.
+    replay: Arc<AtomicBool>,
+    /// Whether any plugin consumed the current frozen key press.
+    key_consumed: bool,
     x11: X11Info,
+    state: SharedState,
+    tx: mpsc::Sender<WmEvent>,
 }
 
 impl PluginManager {
-    pub fn init(x11: X11Info) -> Self {
+    pub fn init(x11: X11Info, state: SharedState, tx: mpsc::Sender<WmEvent>) -> Self {
         let mut plugin_manager = Self {
             store: Default::default(),
             instances: Default::default(),
             events: Default::default(),
-            subscriptions: Arc::new(RwLock::new(SubscriptionManager::new(x11.clone()))),
+            subscriptions: Arc::new(RwLock::new(SubscriptionManager::new(
+                x11.clone(),
+                state.clone(),
+                tx.clone(),
+            ))),
+            replay: Default::default(),
+            key_consumed: false,
             x11,
+            state,
+            tx,
         };
 
         let user_config_dir = PluginManager::get_user_config_dir();
@@ -78,8 +97,11 @@ impl PluginManager {
                 id.clone(),
                 &plugin_manager.store,
                 plugin_manager.x11.clone(),
+                plugin_manager.state.clone(),
+                plugin_manager.tx.clone(),
                 plugin_manager.subscriptions.clone(),
                 plugin_manager.events.clone(),
+                plugin_manager.replay.clone(),
             );
 
             info!("Trying to initialize {}", path.to_string_lossy());
@@ -105,8 +127,11 @@ impl PluginManager {
             };
 
             if let Ok(init) = instance.exports.get_native_function::<(), ()>("init") {
-                init.call().unwrap();
-                info!("Initialized {}", id);
+                // Subscriptions made before a trap stay in effect, like those of a trapping `handle`
+                match init.call() {
+                    Ok(()) => info!("Initialized {}", id),
+                    Err(e) => error!("Plugin {} failed to initialize: {}", id, e),
+                }
             }
 
             plugin_manager.instances.insert(id, instance);
@@ -148,15 +173,16 @@ impl PluginManager {
         path
     }
 
-    pub fn handle(&self, ev: WmEvent) {
-        let sub_lock = self.subscriptions.read();
-        let subs = sub_lock.subscribers(&ev);
+    pub fn handle(&mut self, ev: WmEvent) {
+        // Plugins may subscribe or unsubscribe from their `handle`, so subscriptions must not be
+        // locked during dispatch. Changes take effect starting with the next event.
+        let subs = self.subscriptions.read().subscribers(&ev);
 
         for subscriber in &subs {
             // TODO: optimize locks and clones for read acces
             self.events
                 .write()
-                .entry((*subscriber).clone())
+                .entry(subscriber.clone())
                 .or_default()
                 .lock()
                 .push_back(ev.clone().into());
@@ -165,7 +191,7 @@ impl PluginManager {
         for subscriber in subs {
             info!("Calling handle on {}; Reason: event {:?}", subscriber, ev);
 
-            if let Some(instance) = self.instances.get(subscriber) {
+            if let Some(instance) = self.instances.get(&subscriber) {
                 let handle: NativeFunc<(), ()> =
                     match instance.exports.get_native_function("handle") {
                         Ok(func) => func,
@@ -177,11 +203,37 @@ impl PluginManager {
                             continue;
                         }
                     };
-                handle.call().unwrap();
+                self.replay.store(false, Ordering::Relaxed);
+                // Plugin that trapped did not consume the key press
+                match handle.call() {
+                    Ok(()) if ev.frozen_key().is_some() && !self.replay.load(Ordering::Relaxed) => {
+                        self.key_consumed = true;
+                    }
+                    Ok(()) => {}
+                    Err(e) => error!("Plugin {} failed to handle event: {}", subscriber, e),
+                }
             } else {
                 error!("Unable to find instance for subscriber {}", subscriber);
             }
         }
+
+        if let Some(frozen) = ev.frozen_key().filter(|frozen| frozen.last) {
+            self.release_keyboard(frozen.time);
+        }
+    }
+
+    /// Thaw the keyboard after all events of a frozen key press were dispatched. The key press is
+    /// replayed to the focused client unless a plugin consumed it. Does nothing if the WM has
+    /// already replayed the key press after a timeout.
+    fn release_keyboard(&mut self, time: Timestamp) {
+        let consumed = std::mem::replace(&mut self.key_consumed, false);
+        let result = self
+            .state
+            .write()
+            .release_frozen_key(&self.x11, time, consumed);
+        if let Err(e) = result {
+            error!("Unable to release frozen keyboard: {:?}", e);
+        }
     }
 }
 
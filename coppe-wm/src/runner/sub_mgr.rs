@@ -1,84 +1,219 @@
-use coppe_common::event::{Subscription, SubscriptionEvent, SubscriptionFilter};
+use coppe_common::{
+    button::{Button, ButtonIndex},
+    event::{Subscription, SubscriptionEvent, SubscriptionFilter},
+    key::{Key, Keycode, Keysym, ModMask},
+};
 use log::*;
-use std::collections::HashMap;
-use x11rb::connection::Connection;
-use x11rb::protocol::xproto::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc;
+use std::time::Duration;
+use x11rb::errors::{ConnectionError, ReplyError};
 
 use super::plug_mgr::PluginId;
 use crate::events::WmEvent;
+use crate::wm::{self, SharedState};
 use crate::x11::X11Info;
 
 #[derive(Debug)]
 pub struct SubscriptionManager {
     subs: HashMap<SubscriptionEvent, HashMap<PluginId, Vec<Vec<SubscriptionFilter>>>>,
+    /// Key bindings that are grabbed synchronously because of conditional subscriptions.
+    synchronous: HashSet<SubscriptionEvent>,
+    /// Plugin that holds or last held the keyboard grab.
+    keyboard_grab: Option<PluginId>,
     x11: X11Info,
+    state: SharedState,
+    tx: mpsc::Sender<WmEvent>,
 }
 
 impl SubscriptionManager {
-    pub fn new(x11: X11Info) -> Self {
+    pub fn new(x11: X11Info, state: SharedState, tx: mpsc::Sender<WmEvent>) -> Self {
         Self {
             x11,
+            state,
+            tx,
             subs: Default::default(),
+            synchronous: Default::default(),
+            keyboard_grab: None,
         }
     }
 }
 
 impl SubscriptionManager {
-    pub fn subscribers(&self, ev: &WmEvent) -> Vec<&PluginId> {
-        let sub: SubscriptionEvent = ev.into();
+    /// Plugins subscribed to the event. Returned ids are owned, so that the manager does not have
+    /// to stay locked while the event is dispatched.
+    pub fn subscribers(&self, ev: &WmEvent) -> Vec<PluginId> {
+        let sub = SubscriptionEvent::from(ev);
+        if let SubscriptionEvent::GrabbedKeyPress
+        | SubscriptionEvent::GrabbedKeyRelease
+        | SubscriptionEvent::KeyboardGrabEnd = sub
+        {
+            return self.keyboard_grab.iter().cloned().collect();
+        }
+
+        let sub = self.strip_locks(sub);
+        let wildcard = with_any_modifier(&sub);
 
+        let mut subscribers = Vec::new();
+        for sub in std::iter::once(sub).chain(wildcard) {
+            let matching = self
+                .subs
+                .get(&sub)
+                .into_iter()
+                .flatten()
+                .filter(|(_, filters)| filters.iter().any(|filter_group| ev.matches(filter_group)))
+                .map(|(id, _)| id);
+            for id in matching {
+                if !subscribers.contains(id) {
+                    subscribers.push(id.clone());
+                }
+            }
+        }
+        subscribers
+    }
+
+    /// Remove lock modifiers from key and button bindings, so that they match regardless of
+    /// Caps Lock, Num Lock and Scroll Lock state.
+    fn strip_locks(&self, event: SubscriptionEvent) -> SubscriptionEvent {
+        use SubscriptionEvent::*;
+
+        let state = self.state.read();
+        let strip = |modmask| state.keymap.strip_locks(modmask);
+        match event {
+            KeyPress(key) => KeyPress(Key::new(strip(key.modmask), key.keycode)),
+            KeyRelease(key) => KeyRelease(Key::new(strip(key.modmask), key.keycode)),
+            KeySymPress(modmask, keysym) => KeySymPress(strip(modmask), keysym),
+            ButtonPress(button) => ButtonPress(Button::new(strip(button.modmask), button.index)),
+            event => event,
+        }
+    }
+
+    /// Whether another plugin has a binding that resolves to the same key or button with
+    /// overlapping modifiers.
+    fn has_conflict(&self, id: &PluginId, event: &SubscriptionEvent) -> bool {
+        let state = self.state.read();
+        let keycodes = |keysym| state.keymap.keycodes(keysym);
+        let targets = binding_targets(event, keycodes);
+        if targets.is_empty() {
+            return false;
+        }
         self.subs
-            .get(&sub)
-            .map(|subs| {
-                subs.iter()
-                    .filter(|(_, filters)| {
-                        filters.iter().any(|filter_group| ev.matches(filter_group))
-                    })
-                    .map(|(id, _)| id)
-            })
-            .into_iter()
-            .flatten()
-            .collect::<Vec<&PluginId>>()
+            .iter()
+            .filter(|(_, subs)| subs.keys().any(|other| other != id))
+            .any(|(other, _)| bindings_overlap(&targets, &binding_targets(other, keycodes)))
     }
 
-    pub fn subscribe(&mut self, id: PluginId, sub: Subscription) {
+    /// Add the subscription, grabbing the key or button if it is the first subscription for it.
+    /// Returns `true` if another plugin is bound to the same key or button with overlapping
+    /// modifiers, in which case both plugins may receive the event. Fails if the grab is held by another X client.
+    ///
+    /// Conditional key press bindings are grabbed synchronously, so that the key press can be
+    /// replayed to the focused client if no plugin consumes it.
+    pub fn subscribe(&mut self, id: PluginId, sub: Subscription) -> Result<bool, ReplyError> {
         use std::collections::hash_map::Entry;
-        match self.subs.entry(sub.event.clone()) {
-            Entry::Occupied(mut event_subs) => match event_subs.get_mut().entry(id) {
-                Entry::Occupied(mut filters) => filters.get_mut().push(sub.filters),
-                Entry::Vacant(filters) => {
-                    filters.insert(vec![sub.filters]);
+        let event = self.strip_locks(sub.event);
+        let conflict = self.has_conflict(&id, &event);
+        if conflict {
+            warn!("{}: {:?} is already bound by another plugin", id, event);
+        }
+        let sync = sub.conditional
+            && matches!(
+                event,
+                SubscriptionEvent::KeyPress(_) | SubscriptionEvent::KeySymPress(..)
+            );
+        if sub.conditional && !sync {
+            warn!(
+                "{}: only key presses can be conditional, not {:?}",
+                id, event
+            );
+        }
+
+        match self.subs.entry(event.clone()) {
+            Entry::Occupied(mut event_subs) => {
+                if sync && !self.synchronous.contains(&event) {
+                    info!("Grabbing {:?} synchronously", event);
+                    match event {
+                        SubscriptionEvent::KeyPress(key) => {
+                            // Synchronous grab is added first, so that the key stays grabbed
+                            let mut state = self.state.write();
+                            state.grab_key(&self.x11, key, true)?;
+                            state.ungrab_key(&self.x11, key, false)?;
+                        }
+                        SubscriptionEvent::KeySymPress(modmask, keysym) => {
+                            self.state
+                                .write()
+                                .grab_keysym(&self.x11, modmask, keysym, true)?;
+                        }
+                        _ => {}
+                    }
+                    self.synchronous.insert(event.clone());
                 }
-            },
+
+                match event_subs.get_mut().entry(id) {
+                    Entry::Occupied(mut filters) => filters.get_mut().push(sub.filters),
+                    Entry::Vacant(filters) => {
+                        filters.insert(vec![sub.filters]);
+                    }
+                }
+                Ok(conflict)
+            }
             Entry::Vacant(event_subs) => {
-                info!("Initializing X subscription for {:?}", sub.event);
+                info!("Initializing X subscription for {:?}", event);
                 use SubscriptionEvent::*;
-                match sub.event {
-                    KeyPress(key) | KeyRelease(key) => {
-                        self.x11
-                            .conn
-                            .grab_key(
-                                true,
-                                // FIXME: screen_num should not be hardcoded
-                                self.x11.conn.setup().roots[0].root,
-                                key.modmask,
-                                key.keycode,
-                                GrabMode::ASYNC,
-                                GrabMode::ASYNC,
-                            )
-                            .unwrap();
+                match event {
+                    KeyPress(key) => {
+                        self.state.write().grab_key(&self.x11, key, sync)?;
+                    }
+                    KeyRelease(key) => {
+                        self.state.write().grab_key(&self.x11, key, false)?;
                     }
-                    WindowAdd | WindowRemove | WindowConfigure => {}
+                    KeySymPress(modmask, keysym) => {
+                        self.state
+                            .write()
+                            .grab_keysym(&self.x11, modmask, keysym, sync)?;
+                    }
+                    ButtonPress(button) => {
+                        self.state.write().grab_button(&self.x11, button)?;
+                    }
+                    ClientRequest(kind) => {
+                        self.state.write().intercepted_requests.insert(kind);
+                    }
+                    WindowAdd
+                    | WindowRemove
+                    | WindowConfigure
+                    | WorkspaceChange
+                    | MonitorsChanged
+                    | WindowTitleChanged
+                    | WindowUrgencyChanged
+                    | WindowPropertyChanged
+                    | PointerEnter
+                    | PointerLeave
+                    | WindowFocus
+                    | ButtonRelease
+                    | PointerMotion
+                    | GrabbedKeyPress
+                    | GrabbedKeyRelease
+                    | KeyboardGrabEnd
+                    | WindowClicked => {}
+                }
+                if sync {
+                    self.synchronous.insert(event.clone());
                 }
                 let mut sub_desc = HashMap::new();
                 sub_desc.insert(id, vec![sub.filters]);
                 event_subs.insert(sub_desc);
+                Ok(conflict)
             }
         }
     }
 
-    pub fn unsubscribe(&mut self, id: &PluginId, unsub: &Subscription) {
-        if let Some(subs) = self.subs.get_mut(&unsub.event) {
+    pub fn unsubscribe(
+        &mut self,
+        id: &PluginId,
+        unsub: &Subscription,
+    ) -> Result<(), ConnectionError> {
+        let event = self.strip_locks(unsub.event.clone());
+        if let Some(subs) = self.subs.get_mut(&event) {
             if let Some(filters) = subs.get_mut(id) {
                 if unsub.filters.is_empty() {
                     subs.remove(id);
@@ -92,23 +227,196 @@ impl SubscriptionManager {
             }
 
             if subs.is_empty() {
-                info!("Uninitializing X subscription for {:?}", unsub.event);
+                info!("Uninitializing X subscription for {:?}", event);
+                let sync = self.synchronous.remove(&event);
                 use SubscriptionEvent::*;
-                match unsub.event {
-                    KeyPress(key) | KeyRelease(key) => {
-                        self.x11
-                            .conn
-                            .ungrab_key(
-                                key.keycode,
-                                self.x11.conn.setup().roots[self.x11.screen_num].root,
-                                key.modmask,
-                            )
-                            .unwrap();
+                match event {
+                    KeyPress(key) => {
+                        self.state.write().ungrab_key(&self.x11, key, sync)?;
                     }
-                    WindowAdd | WindowRemove | WindowConfigure => {}
+                    KeyRelease(key) => {
+                        self.state.write().ungrab_key(&self.x11, key, false)?;
+                    }
+                    KeySymPress(modmask, keysym) => {
+                        self.state
+                            .write()
+                            .ungrab_keysym(&self.x11, modmask, keysym)?;
+                    }
+                    ButtonPress(button) => {
+                        self.state.write().ungrab_button(&self.x11, button)?;
+                    }
+                    ClientRequest(kind) => {
+                        self.state.write().intercepted_requests.remove(&kind);
+                    }
+                    WindowAdd
+                    | WindowRemove
+                    | WindowConfigure
+                    | WorkspaceChange
+                    | MonitorsChanged
+                    | WindowTitleChanged
+                    | WindowUrgencyChanged
+                    | WindowPropertyChanged
+                    | PointerEnter
+                    | PointerLeave
+                    | WindowFocus
+                    | ButtonRelease
+                    | PointerMotion
+                    | GrabbedKeyPress
+                    | GrabbedKeyRelease
+                    | KeyboardGrabEnd
+                    | WindowClicked => {}
                 }
-                self.subs.remove(&unsub.event);
+                self.subs.remove(&event);
             }
         }
+        Ok(())
+    }
+
+    /// Grab the keyboard for the plugin, so that it receives all key events until it releases
+    /// the grab. Returns `false` if the keyboard is grabbed by another plugin or X client.
+    pub fn grab_keyboard(&mut self, id: PluginId, timeout: Duration) -> Result<bool, ReplyError> {
+        let mut state = self.state.write();
+        if state.is_keyboard_grabbed() && self.keyboard_grab.as_ref() != Some(&id) {
+            warn!("{}: keyboard is already grabbed by another plugin", id);
+            return Ok(false);
+        }
+
+        let serial = match state.grab_keyboard(&self.x11, timeout)? {
+            Some(serial) => serial,
+            None => return Ok(false),
+        };
+        self.keyboard_grab = Some(id);
+        wm::spawn_grab_watchdog(
+            self.x11.clone(),
+            self.state.clone(),
+            self.tx.clone(),
+            serial,
+        );
+        Ok(true)
+    }
+
+    /// Release the keyboard grab. Returns `false` if the plugin does not hold the grab.
+    pub fn ungrab_keyboard(&mut self, id: &PluginId) -> Result<bool, ConnectionError> {
+        if self.keyboard_grab.as_ref() != Some(id) {
+            return Ok(false);
+        }
+        self.state.write().ungrab_keyboard(&self.x11)
+    }
+}
+
+/// Same binding with [ModMask::ANY], which matches any modifiers. `None` for events that are not
+/// key or button bindings.
+fn with_any_modifier(event: &SubscriptionEvent) -> Option<SubscriptionEvent> {
+    use SubscriptionEvent::*;
+
+    match *event {
+        KeyPress(key) => Some(KeyPress(Key::new(ModMask::ANY, key.keycode))),
+        KeyRelease(key) => Some(KeyRelease(Key::new(ModMask::ANY, key.keycode))),
+        KeySymPress(_, keysym) => Some(KeySymPress(ModMask::ANY, keysym)),
+        ButtonPress(button) => Some(ButtonPress(Button::new(ModMask::ANY, button.index))),
+        _ => None,
+    }
+}
+
+/// Key or button event a binding is grabbed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingTarget {
+    KeyPress(Keycode),
+    KeyRelease(Keycode),
+    ButtonPress(ButtonIndex),
+}
+
+/// Targets and modifiers the binding is grabbed with. Keysyms resolve to all keycodes that
+/// produce them. Empty for events that are not key or button bindings.
+fn binding_targets(
+    event: &SubscriptionEvent,
+    keycodes: impl Fn(Keysym) -> Vec<Keycode>,
+) -> Vec<(BindingTarget, ModMask)> {
+    use SubscriptionEvent::*;
+
+    match *event {
+        KeyPress(key) => vec![(BindingTarget::KeyPress(key.keycode), key.modmask)],
+        KeyRelease(key) => vec![(BindingTarget::KeyRelease(key.keycode), key.modmask)],
+        KeySymPress(modmask, keysym) => keycodes(keysym)
+            .into_iter()
+            .map(|keycode| (BindingTarget::KeyPress(keycode), modmask))
+            .collect(),
+        ButtonPress(button) => vec![(BindingTarget::ButtonPress(button.index), button.modmask)],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether the bindings share a target with the same modifiers or [ModMask::ANY].
+fn bindings_overlap(a: &[(BindingTarget, ModMask)], b: &[(BindingTarget, ModMask)]) -> bool {
+    let is_any = |modmask: ModMask| modmask.contains(ModMask::ANY);
+    a.iter().any(|&(target_a, modmask_a)| {
+        b.iter().any(|&(target_b, modmask_b)| {
+            target_a == target_b
+                && (modmask_a == modmask_b || is_any(modmask_a) || is_any(modmask_b))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use coppe_common::{
+        button::{Button, ButtonIndex},
+        event::SubscriptionEvent::{self, *},
+        key::{Key, Keycode, Keysym, ModMask},
+    };
+
+    use super::{binding_targets, bindings_overlap};
+
+    const A: u8 = 38;
+
+    /// Whether the bindings overlap in a keymap where keysym `a` is produced by keycode [A] only.
+    fn overlap(a: SubscriptionEvent, b: SubscriptionEvent) -> bool {
+        let keycodes = |keysym: Keysym| {
+            if keysym == Keysym::from(u32::from('a')) {
+                vec![Keycode::from(A)]
+            } else {
+                Vec::new()
+            }
+        };
+        bindings_overlap(
+            &binding_targets(&a, keycodes),
+            &binding_targets(&b, keycodes),
+        )
+    }
+
+    fn key(modmask: ModMask, keycode: u8) -> SubscriptionEvent {
+        KeyPress(Key::new(modmask, keycode.into()))
+    }
+
+    fn keysym(modmask: ModMask, c: char) -> SubscriptionEvent {
+        KeySymPress(modmask, Keysym::from(u32::from(c)))
+    }
+
+    #[test]
+    fn same_binding_overlaps() {
+        assert!(overlap(key(ModMask::M4, A), key(ModMask::M4, A)));
+        assert!(!overlap(key(ModMask::M4, A), key(ModMask::M1, A)));
+        assert!(!overlap(key(ModMask::M4, A), key(ModMask::M4, A + 1)));
+        assert!(!overlap(WindowAdd, WindowAdd));
+    }
+
+    #[test]
+    fn any_modifier_overlaps_specific_modifiers() {
+        assert!(overlap(key(ModMask::ANY, A), key(ModMask::M4, A)));
+        assert!(overlap(keysym(ModMask::M4, 'a'), keysym(ModMask::ANY, 'a')));
+        let button = |modmask| ButtonPress(Button::new(modmask, ButtonIndex::from(1)));
+        assert!(overlap(button(ModMask::ANY), button(ModMask::CONTROL)));
+        assert!(!overlap(key(ModMask::ANY, A), key(ModMask::M4, A + 1)));
+    }
+
+    #[test]
+    fn keysym_overlaps_keycode_producing_it() {
+        assert!(overlap(keysym(ModMask::M4, 'a'), key(ModMask::M4, A)));
+        assert!(overlap(key(ModMask::ANY, A), keysym(ModMask::M4, 'a')));
+        assert!(!overlap(keysym(ModMask::M4, 'b'), key(ModMask::M4, A)));
+        assert!(!overlap(
+            keysym(ModMask::M4, 'a'),
+            KeyRelease(Key::new(ModMask::M4, A.into()))
+        ));
     }
 }
@@ -0,0 +1,85 @@
+use coppe_common::window::FocusPolicy;
+use log::*;
+use std::collections::HashSet;
+use x11rb::errors::ConnectionError;
+use x11rb::protocol::xproto::{
+    Allow, ButtonIndex, ButtonPressEvent, ConnectionExt as _, EventMask, GrabMode, ModMask, Window,
+};
+
+use super::state::State;
+use crate::x11::X11Info;
+
+/// Settings of the built-in click-to-focus.
+#[derive(Debug, Clone, Copy)]
+pub struct ClickConfig {
+    /// Whether clicking an unfocused client focuses and raises it with
+    /// [FocusPolicy::Click]. Clicks are reported to plugins with `WindowClicked` either way.
+    pub focus: bool,
+}
+
+impl Default for ClickConfig {
+    fn default() -> Self {
+        Self { focus: true }
+    }
+}
+
+impl State {
+    /// Grab buttons on unfocused clients and release the grab of the focused one, so that only
+    /// the first click on an unfocused client is intercepted. Grabs are only held with
+    /// [FocusPolicy::Click]. Focus changes are applied once per batch of X events.
+    pub fn update_click_grabs(&mut self, x11: &X11Info) -> Result<(), ConnectionError> {
+        let grabbed: HashSet<Window> = if self.focus_policy == FocusPolicy::Click {
+            self.client_list
+                .iter()
+                .copied()
+                .filter(|&win| self.focused != Some(win))
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        for &win in self.click_grabs.difference(&grabbed) {
+            // Grabs of destroyed windows are gone with the window
+            if self.clients.contains_key(&win) {
+                x11.conn
+                    .ungrab_button(ButtonIndex::ANY, win, ModMask::ANY)?;
+            }
+        }
+        for &win in grabbed.difference(&self.click_grabs) {
+            x11.conn.grab_button(
+                false,
+                win,
+                u32::from(EventMask::BUTTON_PRESS) as u16,
+                GrabMode::SYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                ButtonIndex::ANY,
+                ModMask::ANY,
+            )?;
+        }
+        self.click_grabs = grabbed;
+        Ok(())
+    }
+
+    /// Handle button press of the click grab. The clicked client is focused and raised unless
+    /// disabled, then the pointer, which is frozen by the grab, is released and the click is
+    /// replayed to the client.
+    pub fn click(
+        &mut self,
+        x11: &X11Info,
+        event: &ButtonPressEvent,
+    ) -> Result<(), ConnectionError> {
+        let win = event.event;
+        let focused = if self.click_config.focus && self.focus_policy == FocusPolicy::Click {
+            debug!("Focusing clicked window {:?}", win);
+            self.raise_window(x11, win)
+                .and_then(|_| self.focus(x11, win, event.time))
+        } else {
+            Ok(false)
+        };
+        // The pointer stays frozen until the click is replayed, even if focusing failed
+        x11.conn.allow_events(Allow::REPLAY_POINTER, event.time)?;
+        focused.map(|_| ())
+    }
+}
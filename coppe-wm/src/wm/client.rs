@@ -0,0 +1,231 @@
+use coppe_common::window::{Geometry, ScreenId, SizeHints, WindowState, WindowType, WorkspaceId};
+use std::convert::TryFrom;
+use x11rb::cookie::Cookie;
+use x11rb::errors::{ConnectionError, ReplyError};
+use x11rb::properties::{AspectRatio, WmClass, WmHints, WmSizeHints};
+use x11rb::protocol::xproto::*;
+use x11rb::rust_connection::RustConnection;
+
+use super::ewmh;
+use crate::x11::{Atoms, X11Info};
+
+/// Client state as defined by ICCCM `WM_STATE` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WmState {
+    Withdrawn = 0,
+    Normal = 1,
+    Iconic = 3,
+}
+
+/// Cached information about a managed window.
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub id: Window,
+    pub screen: ScreenId,
+    pub state: WmState,
+    /// Number of expected `UnmapNotify` events caused by the WM itself. These should not be
+    /// treated as client withdrawal.
+    pub ignore_unmap: u32,
+    pub geometry: Geometry,
+    /// `WM_CLASS` as `(instance, class)` pair.
+    pub wm_class: Option<(String, String)>,
+    /// `WM_NAME` property. Should only be used as title if `_NET_WM_NAME` is not set.
+    pub wm_name: Option<String>,
+    pub net_wm_name: Option<String>,
+    pub transient_for: Option<Window>,
+    /// `WM_WINDOW_ROLE` property.
+    pub role: Option<String>,
+    /// `_NET_WM_PID` property.
+    pub pid: Option<u32>,
+    /// Known types from `_NET_WM_WINDOW_TYPE`, in order of preference.
+    pub window_types: Vec<WindowType>,
+    /// Atoms listed in `WM_PROTOCOLS`.
+    pub protocols: Vec<Atom>,
+    pub hints: WmHints,
+    /// Size constraints from `WM_NORMAL_HINTS`.
+    pub size_hints: SizeHints,
+    /// Known states from `_NET_WM_STATE`.
+    pub net_wm_state: Vec<WindowState>,
+    pub workspace: WorkspaceId,
+    /// Geometry before the client was made fullscreen or maximized, restored once it leaves
+    /// these states.
+    pub restore_geometry: Option<Geometry>,
+}
+
+impl Client {
+    /// Query all tracked client properties from the X server.
+    pub fn fetch(
+        x11: &X11Info,
+        id: Window,
+        screen: ScreenId,
+        geometry: Geometry,
+    ) -> Result<Self, ReplyError> {
+        let mut client = Self::new(id, screen, geometry);
+
+        let properties = Self::tracked_properties(&x11.atoms);
+        let mut cookies = Vec::with_capacity(properties.len());
+        for &property in properties.iter() {
+            cookies.push(get_property(x11, id, property)?);
+        }
+        for (&property, cookie) in properties.iter().zip(cookies) {
+            client.set_property(&x11.atoms, property, &cookie.reply()?);
+        }
+
+        Ok(client)
+    }
+
+    /// Withdrawn client without any properties.
+    pub fn new(id: Window, screen: ScreenId, geometry: Geometry) -> Self {
+        Self {
+            id,
+            screen,
+            state: WmState::Withdrawn,
+            ignore_unmap: 0,
+            geometry,
+            wm_class: None,
+            wm_name: None,
+            net_wm_name: None,
+            transient_for: None,
+            role: None,
+            pid: None,
+            window_types: Vec::new(),
+            protocols: Vec::new(),
+            hints: WmHints::default(),
+            size_hints: SizeHints::default(),
+            net_wm_state: Vec::new(),
+            workspace: 0,
+            restore_geometry: None,
+        }
+    }
+
+    /// Window title, preferring `_NET_WM_NAME` over `WM_NAME`.
+    pub fn title(&self) -> Option<&str> {
+        self.net_wm_name.as_deref().or(self.wm_name.as_deref())
+    }
+
+    /// Window type as defined by EWMH. Windows without a known `_NET_WM_WINDOW_TYPE` are
+    /// dialogs if they are transient and normal windows otherwise.
+    pub fn window_type(&self) -> WindowType {
+        match self.window_types.first() {
+            Some(&window_type) => window_type,
+            None if self.transient_for.is_some() => WindowType::Dialog,
+            None => WindowType::Normal,
+        }
+    }
+
+    /// Read a changed property of the window. Returns `None` if the property is not tracked.
+    pub fn fetch_property(
+        x11: &X11Info,
+        id: Window,
+        property: Atom,
+    ) -> Result<Option<GetPropertyReply>, ReplyError> {
+        if !Self::tracked_properties(&x11.atoms).contains(&property) {
+            return Ok(None);
+        }
+
+        get_property(x11, id, property)?.reply().map(Some)
+    }
+
+    fn tracked_properties(atoms: &Atoms) -> [Atom; 11] {
+        [
+            AtomEnum::WM_CLASS.into(),
+            AtomEnum::WM_NAME.into(),
+            atoms._NET_WM_NAME,
+            AtomEnum::WM_TRANSIENT_FOR.into(),
+            atoms.WM_PROTOCOLS,
+            AtomEnum::WM_HINTS.into(),
+            AtomEnum::WM_NORMAL_HINTS.into(),
+            atoms._NET_WM_STATE,
+            atoms.WM_WINDOW_ROLE,
+            atoms._NET_WM_PID,
+            atoms._NET_WM_WINDOW_TYPE,
+        ]
+    }
+
+    pub fn set_property(&mut self, atoms: &Atoms, property: Atom, reply: &GetPropertyReply) {
+        let is_empty = reply.type_ == u32::from(AtomEnum::NONE);
+
+        if property == u32::from(AtomEnum::WM_CLASS) {
+            self.wm_class = WmClass::from_reply(reply.clone()).ok().map(|class| {
+                (
+                    String::from_utf8_lossy(class.instance()).into_owned(),
+                    String::from_utf8_lossy(class.class()).into_owned(),
+                )
+            });
+        } else if property == u32::from(AtomEnum::WM_NAME) {
+            self.wm_name = (!is_empty).then(|| String::from_utf8_lossy(&reply.value).into_owned());
+        } else if property == atoms._NET_WM_NAME {
+            self.net_wm_name =
+                (!is_empty).then(|| String::from_utf8_lossy(&reply.value).into_owned());
+        } else if property == u32::from(AtomEnum::WM_TRANSIENT_FOR) {
+            self.transient_for = reply
+                .value32()
+                .and_then(|mut value| value.next())
+                .filter(|&window| window != x11rb::NONE);
+        } else if property == atoms.WM_PROTOCOLS {
+            self.protocols = reply
+                .value32()
+                .map(|value| value.collect())
+                .unwrap_or_default();
+        } else if property == u32::from(AtomEnum::WM_HINTS) {
+            self.hints = WmHints::from_reply(reply).unwrap_or_default();
+        } else if property == u32::from(AtomEnum::WM_NORMAL_HINTS) {
+            self.size_hints = WmSizeHints::from_reply(reply)
+                .map(|hints| size_hints(&hints))
+                .unwrap_or_default();
+        } else if property == atoms._NET_WM_STATE {
+            self.net_wm_state = reply
+                .value32()
+                .map(|value| {
+                    value
+                        .filter_map(|atom| ewmh::state_from_atom(atoms, atom))
+                        .collect()
+                })
+                .unwrap_or_default();
+        } else if property == atoms.WM_WINDOW_ROLE {
+            self.role = (!is_empty).then(|| String::from_utf8_lossy(&reply.value).into_owned());
+        } else if property == atoms._NET_WM_PID {
+            self.pid = reply.value32().and_then(|mut value| value.next());
+        } else if property == atoms._NET_WM_WINDOW_TYPE {
+            self.window_types = reply
+                .value32()
+                .map(|value| {
+                    value
+                        .filter_map(|atom| ewmh::type_from_atom(atoms, atom))
+                        .collect()
+                })
+                .unwrap_or_default();
+        }
+    }
+}
+
+fn size_hints(hints: &WmSizeHints) -> SizeHints {
+    let size = |(width, height): (i32, i32)| (clamp_u16(width), clamp_u16(height));
+    let ratio = |ratio: AspectRatio| {
+        (
+            u32::try_from(ratio.numerator).unwrap_or(0),
+            u32::try_from(ratio.denominator).unwrap_or(0),
+        )
+    };
+
+    SizeHints {
+        min_size: hints.min_size.map(size),
+        max_size: hints.max_size.map(size),
+        base_size: hints.base_size.map(size),
+        increment: hints.size_increment.map(size),
+        aspect: hints.aspect.map(|(min, max)| (ratio(min), ratio(max))),
+    }
+}
+
+fn clamp_u16(value: i32) -> u16 {
+    u16::try_from(value.max(0)).unwrap_or(u16::MAX)
+}
+
+fn get_property(
+    x11: &X11Info,
+    window: Window,
+    property: Atom,
+) -> Result<Cookie<'_, RustConnection, GetPropertyReply>, ConnectionError> {
+    x11.conn
+        .get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX)
+}
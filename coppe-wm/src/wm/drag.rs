@@ -0,0 +1,345 @@
+use coppe_common::{
+    button::{Button, ButtonIndex},
+    key::ModMask,
+    monitor::Monitor,
+    window::{Geometry, MoveResizeDirection},
+};
+use log::*;
+use x11rb::errors::{ConnectionError, ReplyError};
+use x11rb::protocol::xproto::{
+    ButtonPressEvent, ConfigureWindowAux, ConnectionExt as _, EventMask, GrabMode, GrabStatus,
+    Timestamp, Window,
+};
+
+use super::keymap::Keymap;
+use super::state::State;
+use crate::x11::X11Info;
+
+/// Button combinations that start a drag.
+const MOVE_BUTTON: Button = Button {
+    modmask: ModMask::M4,
+    index: ButtonIndex::Left,
+};
+const RESIZE_BUTTON: Button = Button {
+    modmask: ModMask::M4,
+    index: ButtonIndex::Right,
+};
+
+/// Settings of the built-in interactive move and resize.
+#[derive(Debug, Clone, Copy)]
+pub struct DragConfig {
+    /// Whether modifier+drag on a window starts a move or resize. Drags requested by clients or
+    /// plugins are always handled.
+    pub enabled: bool,
+    /// Distance in pixels at which moved windows snap to monitor edges. 0 disables snapping.
+    pub snap_distance: u16,
+}
+
+impl Default for DragConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            snap_distance: 10,
+        }
+    }
+}
+
+/// Interactive move or resize in progress.
+#[derive(Debug, Clone, Copy)]
+pub struct Drag {
+    window: Window,
+    direction: MoveResizeDirection,
+    /// Pointer position at the start of the drag.
+    origin: (i16, i16),
+    /// Window geometry at the start of the drag.
+    geometry: Geometry,
+    /// Latest pointer position that was not yet applied. Motion events are coalesced and applied
+    /// once per batch of X events.
+    pending: Option<(i16, i16)>,
+}
+
+/// Drag direction for a button press on the window with the given geometry, or `None` if the
+/// press should not start a drag. Resizing is done from the window corner closest to the pointer.
+pub fn direction_for_button(
+    event: &ButtonPressEvent,
+    geometry: &Geometry,
+    keymap: &Keymap,
+) -> Option<MoveResizeDirection> {
+    // Only keyboard modifiers are compared, state of other buttons is ignored
+    let button = Button::new(
+        keymap.strip_locks(ModMask::from(event.state & 0xff)),
+        event.detail.into(),
+    );
+
+    if button == MOVE_BUTTON {
+        return Some(MoveResizeDirection::Move);
+    } else if button != RESIZE_BUTTON {
+        return None;
+    }
+
+    let left = i32::from(event.root_x) - i32::from(geometry.x) < i32::from(geometry.width) / 2;
+    let top = i32::from(event.root_y) - i32::from(geometry.y) < i32::from(geometry.height) / 2;
+    Some(match (left, top) {
+        (true, true) => MoveResizeDirection::SizeTopLeft,
+        (false, true) => MoveResizeDirection::SizeTopRight,
+        (true, false) => MoveResizeDirection::SizeBottomLeft,
+        (false, false) => MoveResizeDirection::SizeBottomRight,
+    })
+}
+
+impl State {
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Grab modifier+button combinations that start a drag. Nothing is grabbed if any of them
+    /// is already grabbed by another X client.
+    pub fn grab_drag_buttons(&mut self, x11: &X11Info) -> Result<(), ReplyError> {
+        self.grab_button(x11, MOVE_BUTTON)?;
+        if let Err(e) = self.grab_button(x11, RESIZE_BUTTON) {
+            self.ungrab_button(x11, MOVE_BUTTON)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn ungrab_drag_buttons(&mut self, x11: &X11Info) -> Result<(), ConnectionError> {
+        self.ungrab_button(x11, MOVE_BUTTON)?;
+        self.ungrab_button(x11, RESIZE_BUTTON)
+    }
+
+    /// Enable or disable starting drags with modifier+button.
+    pub fn set_drag_enabled(&mut self, x11: &X11Info, enabled: bool) -> Result<(), ReplyError> {
+        if self.drag_config.enabled == enabled {
+            return Ok(());
+        }
+
+        if enabled {
+            self.grab_drag_buttons(x11)?;
+        } else {
+            self.ungrab_drag_buttons(x11)?;
+        }
+        self.drag_config.enabled = enabled;
+        Ok(())
+    }
+
+    /// Start interactive move or resize of the client from the given pointer position. Returns
+    /// `false` if the window is not managed, the direction is not supported, another drag is in
+    /// progress or the pointer could not be grabbed.
+    pub fn begin_drag(
+        &mut self,
+        x11: &X11Info,
+        win: Window,
+        direction: MoveResizeDirection,
+        origin: (i16, i16),
+        time: Timestamp,
+    ) -> Result<bool, ReplyError> {
+        if self.drag.is_some() {
+            return Ok(false);
+        }
+        let cursor = match x11.cursors.for_direction(direction) {
+            Some(cursor) => cursor,
+            None => return Ok(false),
+        };
+        let (geometry, root) = match self.clients.get(&win) {
+            Some(client) => match x11.screen(client.screen) {
+                Some(screen) => (client.geometry, screen.root),
+                None => return Ok(false),
+            },
+            None => return Ok(false),
+        };
+
+        let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION;
+        let grab = x11
+            .conn
+            .grab_pointer(
+                false,
+                root,
+                u32::from(mask) as u16,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                cursor,
+                time,
+            )?
+            .reply()?;
+        if grab.status != GrabStatus::SUCCESS {
+            warn!(
+                "Unable to grab pointer for {:?}: {:?}",
+                direction, grab.status
+            );
+            return Ok(false);
+        }
+
+        debug!("Starting {:?} of window {:?}", direction, win);
+        self.raise_window(x11, win)?;
+        self.drag = Some(Drag {
+            window: win,
+            direction,
+            origin,
+            geometry,
+            pending: None,
+        });
+        Ok(true)
+    }
+
+    /// Record pointer position during the drag. The window is updated by [State::apply_drag].
+    pub fn drag_motion(&mut self, position: (i16, i16)) {
+        if let Some(drag) = self.drag.as_mut() {
+            drag.pending = Some(position);
+        }
+    }
+
+    /// Move or resize the dragged window to the latest pointer position.
+    pub fn apply_drag(&mut self, x11: &X11Info) -> Result<(), ConnectionError> {
+        let drag = match self.drag.as_mut() {
+            Some(drag) => drag,
+            None => return Ok(()),
+        };
+        let (x, y) = match drag.pending.take() {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+        let client = match self.clients.get(&drag.window) {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+
+        let dx = i32::from(x) - i32::from(drag.origin.0);
+        let dy = i32::from(y) - i32::from(drag.origin.1);
+        let start = drag.geometry;
+
+        let geometry = if drag.direction == MoveResizeDirection::Move {
+            let mut geometry = Geometry {
+                x: clamp_i16(i32::from(start.x) + dx),
+                y: clamp_i16(i32::from(start.y) + dy),
+                ..start
+            };
+            let distance = i32::from(self.drag_config.snap_distance);
+            if let Some(monitors) = self.monitors.get(client.screen as usize) {
+                if distance > 0 {
+                    snap_to_monitors(&mut geometry, monitors.as_slice(), distance);
+                }
+            }
+            geometry
+        } else {
+            use MoveResizeDirection::*;
+
+            let direction = drag.direction;
+            let left = matches!(direction, SizeTopLeft | SizeLeft | SizeBottomLeft);
+            let right = matches!(direction, SizeTopRight | SizeRight | SizeBottomRight);
+            let top = matches!(direction, SizeTopLeft | SizeTop | SizeTopRight);
+            let bottom = matches!(direction, SizeBottomLeft | SizeBottom | SizeBottomRight);
+
+            let dw = if left {
+                -dx
+            } else if right {
+                dx
+            } else {
+                0
+            };
+            let dh = if top {
+                -dy
+            } else if bottom {
+                dy
+            } else {
+                0
+            };
+            let width = i32::from(start.width) + dw;
+            let height = i32::from(start.height) + dh;
+            let (width, height) = client
+                .size_hints
+                .constrain(clamp_size(width), clamp_size(height));
+
+            // Resizing from the left or top edge keeps the opposite edge in place
+            let x = if left {
+                i32::from(start.x) + i32::from(start.width) - i32::from(width)
+            } else {
+                i32::from(start.x)
+            };
+            let y = if top {
+                i32::from(start.y) + i32::from(start.height) - i32::from(height)
+            } else {
+                i32::from(start.y)
+            };
+            Geometry {
+                x: clamp_i16(x),
+                y: clamp_i16(y),
+                width,
+                height,
+            }
+        };
+
+        let aux = ConfigureWindowAux::default()
+            .x(i32::from(geometry.x))
+            .y(i32::from(geometry.y))
+            .width(u32::from(geometry.width))
+            .height(u32::from(geometry.height));
+        x11.conn.configure_window(client.id, &aux)?;
+        Ok(())
+    }
+
+    /// Finish the drag and release the pointer. Returns `false` if there was no drag in
+    /// progress.
+    pub fn end_drag(&mut self, x11: &X11Info, time: Timestamp) -> Result<bool, ConnectionError> {
+        self.apply_drag(x11)?;
+        match self.drag.take() {
+            Some(drag) => {
+                debug!("Finished {:?} of window {:?}", drag.direction, drag.window);
+                x11.conn.ungrab_pointer(time)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Snap window edges to the edges of the closest monitor within `distance`, separately on each
+/// axis.
+fn snap_to_monitors(geometry: &mut Geometry, monitors: &[Monitor], distance: i32) {
+    let x = monitors.iter().find_map(|monitor| {
+        snap(
+            geometry.x,
+            geometry.width,
+            monitor.geometry.x,
+            monitor.geometry.width,
+            distance,
+        )
+    });
+    let y = monitors.iter().find_map(|monitor| {
+        snap(
+            geometry.y,
+            geometry.height,
+            monitor.geometry.y,
+            monitor.geometry.height,
+            distance,
+        )
+    });
+    geometry.x = x.unwrap_or(geometry.x);
+    geometry.y = y.unwrap_or(geometry.y);
+}
+
+/// Snapped position of a window span against a monitor span, or `None` if neither edge is close
+/// enough.
+fn snap(pos: i16, size: u16, monitor_pos: i16, monitor_size: u16, distance: i32) -> Option<i16> {
+    let start = i32::from(monitor_pos);
+    let end = start + i32::from(monitor_size);
+    let size = i32::from(size);
+    let pos = i32::from(pos);
+
+    if (pos - start).abs() <= distance {
+        Some(clamp_i16(start))
+    } else if (pos + size - end).abs() <= distance {
+        Some(clamp_i16(end - size))
+    } else {
+        None
+    }
+}
+
+fn clamp_i16(value: i32) -> i16 {
+    value.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16
+}
+
+fn clamp_size(value: i32) -> u16 {
+    value.clamp(1, i32::from(u16::MAX)) as u16
+}
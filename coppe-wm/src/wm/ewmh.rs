@@ -0,0 +1,327 @@
+use coppe_common::window::{
+    ClientRequest, Geometry, MoveResizeDirection, RequestSource, StateAction, WindowState,
+    WindowType,
+};
+use x11rb::connection::Connection;
+use x11rb::errors::{ConnectionError, ReplyOrIdError};
+use x11rb::protocol::xproto::*;
+use x11rb::wrapper::ConnectionExt as _;
+
+use super::client::Client;
+use super::state::State;
+use crate::x11::{Atoms, X11Info};
+
+const WM_NAME: &str = "coppe-wm";
+
+/// Create `_NET_SUPPORTING_WM_CHECK` window and advertise supported EWMH hints on the root window.
+pub fn init(x11: &X11Info, root: Window) -> Result<Window, ReplyOrIdError> {
+    let atoms = &x11.atoms;
+    let check = x11.conn.generate_id()?;
+    x11.conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        check,
+        root,
+        -1,
+        -1,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::default().override_redirect(1),
+    )?;
+
+    for &win in &[root, check] {
+        x11.conn.change_property32(
+            PropMode::REPLACE,
+            win,
+            atoms._NET_SUPPORTING_WM_CHECK,
+            AtomEnum::WINDOW,
+            &[check],
+        )?;
+    }
+    x11.conn.change_property8(
+        PropMode::REPLACE,
+        check,
+        atoms._NET_WM_NAME,
+        atoms.UTF8_STRING,
+        WM_NAME.as_bytes(),
+    )?;
+
+    let supported = [
+        atoms._NET_SUPPORTED,
+        atoms._NET_SUPPORTING_WM_CHECK,
+        atoms._NET_CLIENT_LIST,
+        atoms._NET_CLIENT_LIST_STACKING,
+        atoms._NET_ACTIVE_WINDOW,
+        atoms._NET_WM_NAME,
+        atoms._NET_CLOSE_WINDOW,
+        atoms._NET_MOVERESIZE_WINDOW,
+        atoms._NET_WM_MOVERESIZE,
+        atoms._NET_NUMBER_OF_DESKTOPS,
+        atoms._NET_CURRENT_DESKTOP,
+        atoms._NET_DESKTOP_NAMES,
+        atoms._NET_WM_DESKTOP,
+        atoms._NET_WM_STATE,
+        atoms._NET_WM_PID,
+        atoms._NET_WM_WINDOW_TYPE,
+    ];
+    let supported: Vec<Atom> = supported
+        .iter()
+        .copied()
+        .chain(
+            WindowState::ALL
+                .iter()
+                .map(|&state| state_atom(atoms, state)),
+        )
+        .chain(
+            WindowType::ALL
+                .iter()
+                .map(|&window_type| type_atom(atoms, window_type)),
+        )
+        .collect();
+    x11.conn.change_property32(
+        PropMode::REPLACE,
+        root,
+        atoms._NET_SUPPORTED,
+        AtomEnum::ATOM,
+        &supported,
+    )?;
+
+    Ok(check)
+}
+
+impl State {
+    /// Publish `_NET_CLIENT_LIST` and `_NET_CLIENT_LIST_STACKING` on every root window.
+    pub fn publish_client_list(&self, x11: &X11Info) -> Result<(), ConnectionError> {
+        for (screen, root) in x11.roots().enumerate() {
+            // Lists may briefly refer to windows that are no longer tracked
+            let on_screen = |win: &&Window| {
+                self.clients
+                    .get(*win)
+                    .is_some_and(|client| client.screen as usize == screen)
+            };
+            let client_list: Vec<Window> =
+                self.client_list.iter().filter(on_screen).copied().collect();
+            let stacking: Vec<Window> = self.stacking.iter().filter(on_screen).copied().collect();
+
+            x11.conn.change_property32(
+                PropMode::REPLACE,
+                root,
+                x11.atoms._NET_CLIENT_LIST,
+                AtomEnum::WINDOW,
+                &client_list,
+            )?;
+            x11.conn.change_property32(
+                PropMode::REPLACE,
+                root,
+                x11.atoms._NET_CLIENT_LIST_STACKING,
+                AtomEnum::WINDOW,
+                &stacking,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Publish `_NET_ACTIVE_WINDOW` on every root window. Roots of screens without the focused
+    /// window get `None`.
+    pub fn publish_active_window(&self, x11: &X11Info) -> Result<(), ConnectionError> {
+        let focused = self
+            .focused
+            .and_then(|win| self.clients.get(&win))
+            .map(|client| (client.id, client.screen as usize));
+
+        for (screen, root) in x11.roots().enumerate() {
+            let active = match focused {
+                Some((win, focused_screen)) if focused_screen == screen => win,
+                _ => x11rb::NONE,
+            };
+            x11.conn.change_property32(
+                PropMode::REPLACE,
+                root,
+                x11.atoms._NET_ACTIVE_WINDOW,
+                AtomEnum::WINDOW,
+                &[active],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Fit the client to its monitor if it is fullscreen or maximized, or restore the geometry
+    /// it had before once it leaves these states.
+    pub fn apply_window_state(
+        &mut self,
+        x11: &X11Info,
+        win: Window,
+    ) -> Result<(), ConnectionError> {
+        let monitor = match self
+            .clients
+            .get(&win)
+            .and_then(|client| self.monitor_of(client))
+        {
+            Some((_, monitor)) => monitor.geometry,
+            None => return Ok(()),
+        };
+        let client = match self.clients.get_mut(&win) {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+
+        let fullscreen = client.net_wm_state.contains(&WindowState::Fullscreen);
+        let vert = client.net_wm_state.contains(&WindowState::MaximizedVert);
+        let horz = client.net_wm_state.contains(&WindowState::MaximizedHorz);
+        let geometry = if fullscreen || vert || horz {
+            let restore = *client.restore_geometry.get_or_insert(client.geometry);
+            if fullscreen {
+                monitor
+            } else {
+                Geometry {
+                    x: if horz { monitor.x } else { restore.x },
+                    y: if vert { monitor.y } else { restore.y },
+                    width: if horz { monitor.width } else { restore.width },
+                    height: if vert { monitor.height } else { restore.height },
+                }
+            }
+        } else {
+            match client.restore_geometry.take() {
+                Some(restore) => restore,
+                None => return Ok(()),
+            }
+        };
+
+        let aux = ConfigureWindowAux::default()
+            .x(i32::from(geometry.x))
+            .y(i32::from(geometry.y))
+            .width(u32::from(geometry.width))
+            .height(u32::from(geometry.height));
+        x11.conn.configure_window(win, &aux)?;
+        if fullscreen {
+            self.raise_window(x11, win)?;
+        }
+        Ok(())
+    }
+}
+
+/// Publish `_NET_WM_STATE` of the client.
+pub fn publish_window_state(x11: &X11Info, client: &Client) -> Result<(), ConnectionError> {
+    let states: Vec<Atom> = client
+        .net_wm_state
+        .iter()
+        .map(|&state| state_atom(&x11.atoms, state))
+        .collect();
+    x11.conn.change_property32(
+        PropMode::REPLACE,
+        client.id,
+        x11.atoms._NET_WM_STATE,
+        AtomEnum::ATOM,
+        &states,
+    )?;
+    Ok(())
+}
+
+/// Decode EWMH client message. Returns `None` if message is not a supported EWMH request.
+pub fn decode_request(atoms: &Atoms, event: &ClientMessageEvent) -> Option<ClientRequest> {
+    if event.format != 32 {
+        return None;
+    }
+    let data = event.data.as_data32();
+    let window = event.window;
+
+    let request = if event.type_ == atoms._NET_ACTIVE_WINDOW {
+        let source = match data[0] {
+            1 => RequestSource::Application,
+            2 => RequestSource::Pager,
+            _ => RequestSource::Unknown,
+        };
+        ClientRequest::Activate { window, source }
+    } else if event.type_ == atoms._NET_CLOSE_WINDOW {
+        ClientRequest::Close { window }
+    } else if event.type_ == atoms._NET_WM_STATE {
+        let action = match data[0] {
+            0 => StateAction::Remove,
+            1 => StateAction::Add,
+            2 => StateAction::Toggle,
+            _ => return None,
+        };
+        let mut states = data[1..3]
+            .iter()
+            .filter_map(|&atom| state_from_atom(atoms, atom));
+        ClientRequest::State {
+            window,
+            action,
+            first: states.next()?,
+            second: states.next(),
+        }
+    } else if event.type_ == atoms._NET_MOVERESIZE_WINDOW {
+        let field = |bit: u32, value: u32| (data[0] & (1 << bit) != 0).then_some(value);
+        ClientRequest::MoveResize {
+            window,
+            x: field(8, data[1]).map(|x| x as i16),
+            y: field(9, data[2]).map(|y| y as i16),
+            width: field(10, data[3]).map(|width| width as u16),
+            height: field(11, data[4]).map(|height| height as u16),
+        }
+    } else if event.type_ == atoms._NET_WM_MOVERESIZE {
+        ClientRequest::InteractiveMoveResize {
+            window,
+            x_root: data[0] as i16,
+            y_root: data[1] as i16,
+            direction: MoveResizeDirection::from_u32(data[2])?,
+            button: data[3] as u8,
+        }
+    } else {
+        return None;
+    };
+
+    Some(request)
+}
+
+pub fn state_atom(atoms: &Atoms, state: WindowState) -> Atom {
+    match state {
+        WindowState::Modal => atoms._NET_WM_STATE_MODAL,
+        WindowState::Sticky => atoms._NET_WM_STATE_STICKY,
+        WindowState::MaximizedVert => atoms._NET_WM_STATE_MAXIMIZED_VERT,
+        WindowState::MaximizedHorz => atoms._NET_WM_STATE_MAXIMIZED_HORZ,
+        WindowState::Shaded => atoms._NET_WM_STATE_SHADED,
+        WindowState::SkipTaskbar => atoms._NET_WM_STATE_SKIP_TASKBAR,
+        WindowState::SkipPager => atoms._NET_WM_STATE_SKIP_PAGER,
+        WindowState::Hidden => atoms._NET_WM_STATE_HIDDEN,
+        WindowState::Fullscreen => atoms._NET_WM_STATE_FULLSCREEN,
+        WindowState::Above => atoms._NET_WM_STATE_ABOVE,
+        WindowState::Below => atoms._NET_WM_STATE_BELOW,
+        WindowState::DemandsAttention => atoms._NET_WM_STATE_DEMANDS_ATTENTION,
+    }
+}
+
+pub fn type_atom(atoms: &Atoms, window_type: WindowType) -> Atom {
+    match window_type {
+        WindowType::Normal => atoms._NET_WM_WINDOW_TYPE_NORMAL,
+        WindowType::Desktop => atoms._NET_WM_WINDOW_TYPE_DESKTOP,
+        WindowType::Dock => atoms._NET_WM_WINDOW_TYPE_DOCK,
+        WindowType::Toolbar => atoms._NET_WM_WINDOW_TYPE_TOOLBAR,
+        WindowType::Menu => atoms._NET_WM_WINDOW_TYPE_MENU,
+        WindowType::Utility => atoms._NET_WM_WINDOW_TYPE_UTILITY,
+        WindowType::Splash => atoms._NET_WM_WINDOW_TYPE_SPLASH,
+        WindowType::Dialog => atoms._NET_WM_WINDOW_TYPE_DIALOG,
+        WindowType::DropdownMenu => atoms._NET_WM_WINDOW_TYPE_DROPDOWN_MENU,
+        WindowType::PopupMenu => atoms._NET_WM_WINDOW_TYPE_POPUP_MENU,
+        WindowType::Tooltip => atoms._NET_WM_WINDOW_TYPE_TOOLTIP,
+        WindowType::Notification => atoms._NET_WM_WINDOW_TYPE_NOTIFICATION,
+        WindowType::Combo => atoms._NET_WM_WINDOW_TYPE_COMBO,
+        WindowType::Dnd => atoms._NET_WM_WINDOW_TYPE_DND,
+    }
+}
+
+pub fn type_from_atom(atoms: &Atoms, atom: Atom) -> Option<WindowType> {
+    WindowType::ALL
+        .iter()
+        .copied()
+        .find(|&window_type| type_atom(atoms, window_type) == atom)
+}
+
+pub fn state_from_atom(atoms: &Atoms, atom: Atom) -> Option<WindowState> {
+    WindowState::ALL
+        .iter()
+        .copied()
+        .find(|&state| state_atom(atoms, state) == atom)
+}
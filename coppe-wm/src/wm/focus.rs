@@ -0,0 +1,161 @@
+use coppe_common::event::Event;
+use x11rb::errors::ConnectionError;
+use x11rb::protocol::xproto::*;
+
+use super::state::State;
+use crate::x11::X11Info;
+
+/// ICCCM input model, derived from `WM_HINTS.input` and `WM_TAKE_FOCUS` support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputModel {
+    /// Client never expects keyboard input.
+    NoInput,
+    /// Client expects the WM to set input focus.
+    Passive,
+    /// Client expects the WM to set input focus and may move it between its own windows.
+    LocallyActive,
+    /// Client sets input focus itself when asked with `WM_TAKE_FOCUS`.
+    GloballyActive,
+}
+
+impl State {
+    /// Whether the window is a client that is mapped on the current workspace. Only such clients
+    /// can be focused or raised.
+    pub fn is_viewable(&self, win: Window) -> bool {
+        self.clients
+            .get(&win)
+            .is_some_and(|client| client.is_visible(self.current_workspace))
+    }
+
+    /// Give input focus to the client according to its ICCCM input model and publish
+    /// `_NET_ACTIVE_WINDOW`. Returns `false` if the window is not viewable or does not accept
+    /// focus.
+    pub fn focus(
+        &mut self,
+        x11: &X11Info,
+        win: Window,
+        time: Timestamp,
+    ) -> Result<bool, ConnectionError> {
+        let client = match self.clients.get(&win) {
+            Some(client) if client.is_visible(self.current_workspace) => client,
+            _ => return Ok(false),
+        };
+
+        // ICCCM: clients without an input hint are assumed to accept focus
+        let input = client.hints.input.unwrap_or(true);
+        let take_focus = client.protocols.contains(&x11.atoms.WM_TAKE_FOCUS);
+        let model = match (input, take_focus) {
+            (false, false) => InputModel::NoInput,
+            (true, false) => InputModel::Passive,
+            (true, true) => InputModel::LocallyActive,
+            (false, true) => InputModel::GloballyActive,
+        };
+
+        match model {
+            InputModel::NoInput => return Ok(false),
+            InputModel::Passive => {
+                x11.conn.set_input_focus(InputFocus::PARENT, win, time)?;
+            }
+            InputModel::LocallyActive => {
+                x11.conn.set_input_focus(InputFocus::PARENT, win, time)?;
+                x11.send_protocol(win, x11.atoms.WM_TAKE_FOCUS, time)?;
+            }
+            InputModel::GloballyActive => {
+                x11.send_protocol(win, x11.atoms.WM_TAKE_FOCUS, time)?;
+            }
+        }
+
+        self.focused = Some(win);
+        self.publish_active_window(x11)?;
+        Ok(true)
+    }
+
+    /// Drop focus, returning it to the window under the pointer.
+    pub fn unfocus(&mut self, x11: &X11Info, time: Timestamp) -> Result<(), ConnectionError> {
+        x11.conn
+            .set_input_focus(InputFocus::POINTER_ROOT, InputFocus::POINTER_ROOT, time)?;
+        if self.focused.take().is_some() {
+            self.publish_active_window(x11)?;
+        }
+        Ok(())
+    }
+
+    /// Record input focus as reported by the X server, which includes focus changes made by
+    /// clients themselves. Returns the event that should be dispatched to plugins, or `None` if
+    /// focused client has not changed since the last report.
+    pub fn update_focus(
+        &mut self,
+        x11: &X11Info,
+        win: Option<Window>,
+    ) -> Result<Option<Event>, ConnectionError> {
+        let win = win.filter(|win| self.clients.contains_key(win));
+        if self.focused != win {
+            self.focused = win;
+            self.publish_active_window(x11)?;
+        }
+
+        if self.reported_focus == win {
+            return Ok(None);
+        }
+        let previous = std::mem::replace(&mut self.reported_focus, win);
+        Ok(Some(Event::WindowFocus {
+            previous,
+            current: win,
+        }))
+    }
+
+    /// Place the client on top of the stack. Returns `false` if the window is not viewable.
+    pub fn raise_window(&mut self, x11: &X11Info, win: Window) -> Result<bool, ConnectionError> {
+        if !self.is_viewable(win) {
+            return Ok(false);
+        }
+
+        let aux = ConfigureWindowAux::default().stack_mode(StackMode::ABOVE);
+        x11.conn.configure_window(win, &aux)?;
+        if self.raise(win) {
+            self.publish_client_list(x11)?;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coppe_common::window::{Geometry, ALL_WORKSPACES};
+
+    use crate::wm::{Client, State, WmState};
+
+    fn state_with_client(state: WmState, workspace: u32) -> State {
+        let geometry = Geometry {
+            x: 0,
+            y: 0,
+            width: 100,
+            height: 100,
+        };
+        let mut client = Client::new(1, 0, geometry);
+        client.state = state;
+        client.workspace = workspace;
+
+        let mut state = State {
+            workspaces: vec!["1".into(), "2".into()],
+            ..State::default()
+        };
+        state.clients.insert(1, client);
+        state
+    }
+
+    #[test]
+    fn viewable_clients() {
+        assert!(state_with_client(WmState::Normal, 0).is_viewable(1));
+        assert!(state_with_client(WmState::Normal, ALL_WORKSPACES).is_viewable(1));
+        assert!(!State::default().is_viewable(1));
+    }
+
+    #[test]
+    fn unmapped_clients_are_not_viewable() {
+        assert!(!state_with_client(WmState::Withdrawn, 0).is_viewable(1));
+        assert!(!state_with_client(WmState::Iconic, 0).is_viewable(1));
+        // Client on a hidden workspace
+        assert!(!state_with_client(WmState::Normal, 1).is_viewable(1));
+    }
+}
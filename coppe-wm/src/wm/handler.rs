@@ -1,29 +1,168 @@
+use coppe_common::{
+    event::Event,
+    key::Key,
+    window::{
+        ClientRequest, FocusPolicy, Geometry, MoveResizeDirection, StateAction, ALL_WORKSPACES,
+    },
+};
 use log::*;
-use x11rb::errors::{ReplyError, ReplyOrIdError};
-use x11rb::protocol::{xproto::*, Event as XEvent};
+use x11rb::errors::{ConnectionError, ReplyError, ReplyOrIdError};
+use x11rb::protocol::{xproto::*, ErrorKind, Event as XEvent};
 use x11rb::CURRENT_TIME;
 
-use super::WindowManager;
-use crate::events::WmEvent;
+use super::{
+    client::{Client, WmState},
+    drag, ewmh, ignore_destroyed, keyboard, keymap, monitor, WindowManager,
+};
+use crate::events::{FrozenKey, WmEvent};
 
 impl WindowManager {
     pub fn handle_event(&mut self, event: XEvent) -> Result<(), ReplyOrIdError> {
         debug!("Got X11 event {:?}", event);
-        WmEvent::try_from(&event).map(|e| self.tx.send(e));
+        // Key events during a keyboard grab are only reported as grabbed keys, key presses that
+        // froze the keyboard are dispatched by `handle_key_press`
+        let skip = match &event {
+            XEvent::KeyPress(event) => {
+                let state = self.state.read();
+                state.is_keyboard_grabbed()
+                    || state.is_sync_grabbed(event.root, event.state.into(), event.detail.into())
+            }
+            XEvent::KeyRelease(_) => self.state.read().is_keyboard_grabbed(),
+            _ => false,
+        };
+        if !skip {
+            if let Some(e) = WmEvent::try_from(&self.x11, &self.state.read(), &event) {
+                self.send(e);
+            }
+        }
 
         match event {
             XEvent::UnmapNotify(event) => self.handle_unmap_notify(event)?,
+            XEvent::DestroyNotify(event) => self.handle_destroy_notify(event)?,
+            XEvent::ConfigureNotify(event) => self.handle_configure_notify(event),
+            XEvent::PropertyNotify(event) => self.handle_property_notify(event)?,
             XEvent::ConfigureRequest(event) => self.handle_configure_request(event)?,
             XEvent::MapRequest(event) => self.handle_map_request(event)?,
             XEvent::Expose(event) => self.handle_expose(event)?,
             XEvent::EnterNotify(event) => self.handle_enter(event)?,
+            XEvent::LeaveNotify(event) => self.handle_leave(event),
+            XEvent::FocusIn(event) => self.handle_focus_in(event)?,
+            XEvent::KeyPress(event) => self.handle_key_press(event)?,
+            XEvent::KeyRelease(event) => self.handle_key_release(event)?,
+            XEvent::MappingNotify(event) => self.handle_mapping_notify(event)?,
+            XEvent::ButtonPress(event) => self.handle_button_press(event)?,
+            XEvent::ButtonRelease(event) => self.handle_button_release(event)?,
+            XEvent::MotionNotify(event) => self.handle_motion(event),
+            XEvent::ClientMessage(event) => self.handle_client_message(event)?,
+            XEvent::RandrScreenChangeNotify(_) | XEvent::RandrNotify(_) => {
+                self.handle_screen_change()?
+            }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_unmap_notify(&self, event: UnmapNotifyEvent) -> Result<(), ReplyError> {
-        self.x11.conn.destroy_window(event.window).unwrap();
+    fn handle_unmap_notify(&mut self, event: UnmapNotifyEvent) -> Result<(), ReplyError> {
+        {
+            let mut state = self.state.write();
+            let client = match state.clients.get_mut(&event.window) {
+                Some(client) => client,
+                None => return Ok(()),
+            };
+
+            // Synthetic events are sent by clients to request withdrawal of an already unmapped
+            // window and must never be ignored
+            let synthetic = event.response_type & 0x80 != 0;
+            if client.ignore_unmap > 0 && !synthetic {
+                client.ignore_unmap -= 1;
+                return Ok(());
+            }
+        }
+
+        self.withdraw_window(event.window)?;
+        Ok(())
+    }
+
+    fn handle_destroy_notify(&mut self, event: DestroyNotifyEvent) -> Result<(), ReplyError> {
+        self.unmanage_window(event.window)?;
+        Ok(())
+    }
+
+    fn handle_configure_notify(&mut self, event: ConfigureNotifyEvent) {
+        if let Some(client) = self.state.write().clients.get_mut(&event.window) {
+            client.geometry = Geometry {
+                x: event.x,
+                y: event.y,
+                width: event.width,
+                height: event.height,
+            };
+        }
+    }
+
+    fn handle_property_notify(&mut self, event: PropertyNotifyEvent) -> Result<(), ReplyError> {
+        if !self.state.read().clients.contains_key(&event.window) {
+            return Ok(());
+        }
+
+        // The property is read without holding the state, the client may be gone afterwards
+        let reply = match Client::fetch_property(&self.x11, event.window, event.atom) {
+            Ok(reply) => reply,
+            Err(ReplyError::X11Error(e)) if e.error_kind == ErrorKind::Window => {
+                debug!(
+                    "Window {:?} destroyed before reading property",
+                    event.window
+                );
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut state = self.state.write();
+        let client = match state.clients.get_mut(&event.window) {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+
+        let title = client.title().map(ToString::to_string);
+        let urgent = client.hints.urgent;
+        if let Some(reply) = &reply {
+            client.set_property(&self.x11.atoms, event.atom, reply);
+        }
+        let title_changed = client.title() != title.as_deref();
+        let urgency_changed = (client.hints.urgent != urgent).then_some(client.hints.urgent);
+
+        if title_changed {
+            self.emit(&state, Event::WindowTitleChanged(event.window));
+        }
+        if let Some(urgent) = urgency_changed {
+            self.emit(
+                &state,
+                Event::WindowUrgencyChanged {
+                    window: event.window,
+                    urgent,
+                },
+            );
+        }
+        // Properties maintained by the WM itself are not reported
+        let atoms = &self.x11.atoms;
+        if ![atoms.WM_STATE, atoms._NET_WM_STATE, atoms._NET_WM_DESKTOP].contains(&event.atom) {
+            self.emit(
+                &state,
+                Event::WindowPropertyChanged {
+                    window: event.window,
+                    atom: event.atom,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn handle_screen_change(&mut self) -> Result<(), ReplyError> {
+        let monitors = monitor::query_all(&self.x11)?;
+        let mut state = self.state.write();
+        for event in state.update_monitors(monitors) {
+            self.emit(&state, event);
+        }
         Ok(())
     }
 
@@ -41,18 +180,33 @@ impl WindowManager {
         if event.value_mask & u16::from(ConfigWindow::HEIGHT) != 0 {
             aux = aux.height(u32::from(event.height))
         }
+        self.apply_size_hints(event.window, &mut aux);
         debug!("Configure window: {:?}", aux);
         self.x11.conn.configure_window(event.window, &aux)?;
         Ok(())
     }
 
-    fn handle_map_request(&self, event: MapRequestEvent) -> Result<(), ReplyError> {
-        self.manage_window(
-            event.window,
-            &self.x11.conn.get_geometry(event.window)?.reply()?,
-        )
-        .unwrap();
-        Ok(())
+    /// Adjust requested size of a managed client to its `WM_NORMAL_HINTS`.
+    fn apply_size_hints(&self, win: Window, aux: &mut ConfigureWindowAux) {
+        if aux.width.is_none() && aux.height.is_none() {
+            return;
+        }
+        if let Some(client) = self.state.read().clients.get(&win) {
+            let width = aux.width.map_or(client.geometry.width, |w| w as u16);
+            let height = aux.height.map_or(client.geometry.height, |h| h as u16);
+            let (width, height) = client.size_hints.constrain(width, height);
+            aux.width = Some(u32::from(width));
+            aux.height = Some(u32::from(height));
+        }
+    }
+
+    fn handle_map_request(&mut self, event: MapRequestEvent) -> Result<(), ReplyOrIdError> {
+        let geometry = self.x11.conn.get_geometry(event.window)?.reply();
+        let result = match geometry {
+            Ok(geometry) => self.manage_window(event.window, &geometry),
+            Err(e) => Err(e.into()),
+        };
+        ignore_destroyed(event.window, result)
     }
 
     fn handle_expose(&mut self, event: ExposeEvent) -> Result<(), ReplyError> {
@@ -61,29 +215,305 @@ impl WindowManager {
     }
 
     fn handle_enter(&self, event: EnterNotifyEvent) -> Result<(), ReplyError> {
-        let window = event.event;
-
-        let data = [self.x11.atoms.WM_TAKE_FOCUS, CURRENT_TIME, 0, 0, 0];
-        let event = ClientMessageEvent {
-            response_type: CLIENT_MESSAGE_EVENT,
-            format: 32,
-            sequence: 0,
-            window,
-            type_: self.x11.atoms.WM_PROTOCOLS,
-            data: data.into(),
+        // Crossing events caused by grabs and moves between a window and its children do not
+        // mean that the pointer moved to another window
+        if event.mode != NotifyMode::NORMAL {
+            return Ok(());
+        }
+
+        let mut state = self.state.write();
+        if event.event == event.root {
+            if event.detail == NotifyDetail::INFERIOR
+                && state.focus_policy == FocusPolicy::FollowMouse
+            {
+                state.unfocus(&self.x11, event.time)?;
+            }
+            return Ok(());
+        }
+        if event.detail == NotifyDetail::INFERIOR || !state.clients.contains_key(&event.event) {
+            return Ok(());
+        }
+
+        self.emit(&state, Event::PointerEnter(event.event));
+        match state.focus_policy {
+            FocusPolicy::FollowMouse => {
+                state.raise_window(&self.x11, event.event)?;
+                state.focus(&self.x11, event.event, event.time)?;
+            }
+            FocusPolicy::Sloppy => {
+                state.focus(&self.x11, event.event, event.time)?;
+            }
+            // Clicks are handled by `handle_button_press`
+            FocusPolicy::Click | FocusPolicy::None => {}
+        }
+        Ok(())
+    }
+
+    fn handle_leave(&self, event: LeaveNotifyEvent) {
+        if event.mode != NotifyMode::NORMAL || event.detail == NotifyDetail::INFERIOR {
+            return;
+        }
+        let state = self.state.read();
+        if state.clients.contains_key(&event.event) {
+            self.emit(&state, Event::PointerLeave(event.event));
+        }
+    }
+
+    /// Track actual input focus. Focus leaving a client is seen as `FocusIn` on another client
+    /// or on the root window, so `FocusOut` events need no handling.
+    fn handle_focus_in(&self, event: FocusInEvent) -> Result<(), ReplyError> {
+        // Keyboard grabs only temporarily redirect focus. `Pointer` detail is sent to the window
+        // under the pointer, not to the window that has focus.
+        if event.mode == NotifyMode::GRAB
+            || event.mode == NotifyMode::UNGRAB
+            || event.detail == NotifyDetail::POINTER
+        {
+            return Ok(());
+        }
+
+        let mut state = self.state.write();
+        let focused = if self.x11.screen_of(event.event).is_some() {
+            // Focus moved to a child of the root window. It is reported to that child instead.
+            if event.detail == NotifyDetail::VIRTUAL
+                || event.detail == NotifyDetail::NONLINEAR_VIRTUAL
+            {
+                return Ok(());
+            }
+            None
+        } else {
+            Some(event.event)
+        };
+
+        if let Some(event) = state.update_focus(&self.x11, focused)? {
+            self.emit(&state, event);
+        }
+        Ok(())
+    }
+
+    fn handle_key_press(&self, event: KeyPressEvent) -> Result<(), ConnectionError> {
+        let mut state = self.state.write();
+        if state.is_keyboard_grabbed() {
+            match state.grabbed_key(&self.x11, &event, true)? {
+                Some(input) => self.emit(&state, Event::GrabbedKeyPress(input)),
+                None => self.emit(&state, Event::KeyboardGrabEnd),
+            }
+            return Ok(());
+        }
+
+        let modmask = keymap::key_modifiers(event.state.into());
+        let keycode = event.detail.into();
+        let mut events: Vec<_> = state
+            .bound_keysyms(modmask, keycode)
+            .into_iter()
+            .map(|keysym| Event::KeySymPress(modmask, keysym))
+            .collect();
+        if !state.is_sync_grabbed(event.root, modmask, keycode) {
+            events.into_iter().for_each(|e| self.emit(&state, e));
+            return Ok(());
+        }
+
+        // The keyboard stays frozen until the runner has dispatched all events of the key press
+        // and either consumed it or replayed it to the focused client, or until the watchdog
+        // replays it
+        state.frozen_key = Some(event.time);
+        keyboard::spawn_frozen_key_watchdog(self.x11.clone(), self.state.clone(), event.time);
+        events.insert(0, Event::KeyPress(Key::new(modmask, keycode)));
+        let count = events.len();
+        for (i, e) in events.into_iter().enumerate() {
+            let frozen = FrozenKey {
+                time: event.time,
+                last: i + 1 == count,
+            };
+            self.send(WmEvent::with_frozen_key(e, frozen, &state));
+        }
+        Ok(())
+    }
+
+    fn handle_key_release(&self, event: KeyReleaseEvent) -> Result<(), ConnectionError> {
+        let mut state = self.state.write();
+        if state.is_keyboard_grabbed() {
+            if let Some(input) = state.grabbed_key(&self.x11, &event, false)? {
+                self.emit(&state, Event::GrabbedKeyRelease(input));
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_mapping_notify(&self, event: MappingNotifyEvent) -> Result<(), ReplyError> {
+        if event.request == Mapping::KEYBOARD || event.request == Mapping::MODIFIER {
+            info!("Keyboard mapping changed");
+            self.state.write().update_keymap(&self.x11)?;
+        }
+        Ok(())
+    }
+
+    /// Focus clicked client with click-to-focus, or start built-in move or resize on
+    /// modifier+button press over a managed window.
+    fn handle_button_press(&self, event: ButtonPressEvent) -> Result<(), ReplyError> {
+        let mut state = self.state.write();
+        // Only the click-to-focus grab reports button presses on client windows
+        if event.event != event.root {
+            state.click(&self.x11, &event)?;
+            return Ok(());
+        }
+        if !state.drag_config.enabled || state.is_dragging() {
+            return Ok(());
+        }
+
+        let geometry = match state.clients.get(&event.child) {
+            Some(client) => client.geometry,
+            None => return Ok(()),
         };
+        if let Some(direction) = drag::direction_for_button(&event, &geometry, &state.keymap) {
+            state.begin_drag(
+                &self.x11,
+                event.child,
+                direction,
+                (event.root_x, event.root_y),
+                event.time,
+            )?;
+        }
+        Ok(())
+    }
 
-        self.x11
-            .conn
-            .send_event(false, window, EventMask::NO_EVENT, &event)?
-            .check()?;
+    fn handle_button_release(&self, event: ButtonReleaseEvent) -> Result<(), ReplyError> {
+        self.state.write().end_drag(&self.x11, event.time)?;
+        Ok(())
+    }
 
-        let aux = ConfigureWindowAux::default().stack_mode(StackMode::ABOVE);
-        self.x11.conn.configure_window(window, &aux)?;
+    fn handle_motion(&self, event: MotionNotifyEvent) {
+        self.state.write().drag_motion((event.root_x, event.root_y));
+    }
 
-        self.x11
-            .conn
-            .set_input_focus(InputFocus::PARENT, window, CURRENT_TIME)?;
+    fn handle_client_message(&mut self, event: ClientMessageEvent) -> Result<(), ReplyError> {
+        if event.type_ == self.x11.atoms.WM_CHANGE_STATE
+            && event.format == 32
+            && event.data.as_data32()[0] == WmState::Iconic as u32
+        {
+            info!("Window {:?} requested iconification", event.window);
+            self.unmap_window(event.window, WmState::Iconic)?;
+        } else if event.type_ == self.x11.atoms._NET_CURRENT_DESKTOP && event.format == 32 {
+            let workspace = event.data.as_data32()[0];
+            let mut state = self.state.write();
+            if let Some(change) = state.switch_workspace(&self.x11, workspace)? {
+                self.emit(&state, change);
+            }
+        } else if event.type_ == self.x11.atoms._NET_WM_DESKTOP && event.format == 32 {
+            let workspace = event.data.as_data32()[0];
+            self.state
+                .write()
+                .move_to_workspace(&self.x11, event.window, workspace)?;
+        } else if let Some(request) = ewmh::decode_request(&self.x11.atoms, &event) {
+            debug!("Got client request {:?}", request);
+            let intercepted = {
+                let state = self.state.read();
+                self.emit(&state, Event::ClientRequest(request));
+                state.intercepted_requests.contains(&request.kind())
+            };
+            if !intercepted {
+                self.handle_client_request(request)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Default handling of EWMH requests, used unless a plugin subscribed to the request.
+    fn handle_client_request(&mut self, request: ClientRequest) -> Result<(), ReplyError> {
+        if !self.state.read().clients.contains_key(&request.window()) {
+            return Ok(());
+        }
+
+        match request {
+            ClientRequest::Activate { window, .. } => {
+                {
+                    let mut state = self.state.write();
+                    // Withdrawn clients can not be shown until they map themselves again
+                    let workspace = match state.clients.get(&window) {
+                        Some(client) if client.state != WmState::Withdrawn => client.workspace,
+                        _ => return Ok(()),
+                    };
+                    let change = if workspace != ALL_WORKSPACES {
+                        state.switch_workspace(&self.x11, workspace)?
+                    } else {
+                        None
+                    };
+                    if let Some(change) = change {
+                        self.emit(&state, change);
+                    }
+                }
+                self.deiconify_window(window)?;
+                self.focus_window(window)?;
+            }
+            ClientRequest::Close { window } => self.close_window(window)?,
+            ClientRequest::State {
+                window,
+                action,
+                first,
+                second,
+            } => {
+                let mut state = self.state.write();
+                if let Some(client) = state.clients.get_mut(&window) {
+                    for &window_state in [Some(first), second].iter().flatten() {
+                        let present = client.net_wm_state.contains(&window_state);
+                        let add = match action {
+                            StateAction::Add => true,
+                            StateAction::Remove => false,
+                            StateAction::Toggle => !present,
+                        };
+                        if add && !present {
+                            client.net_wm_state.push(window_state);
+                        } else if !add {
+                            client.net_wm_state.retain(|&s| s != window_state);
+                        }
+                    }
+                    ewmh::publish_window_state(&self.x11, client)?;
+                    state.apply_window_state(&self.x11, window)?;
+                }
+            }
+            ClientRequest::MoveResize {
+                window,
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let mut aux = ConfigureWindowAux {
+                    x: x.map(i32::from),
+                    y: y.map(i32::from),
+                    width: width.map(u32::from),
+                    height: height.map(u32::from),
+                    ..Default::default()
+                };
+                self.apply_size_hints(window, &mut aux);
+                self.x11.conn.configure_window(window, &aux)?;
+            }
+            ClientRequest::InteractiveMoveResize {
+                window,
+                x_root,
+                y_root,
+                direction,
+                ..
+            } => {
+                let mut state = self.state.write();
+                match direction {
+                    MoveResizeDirection::Cancel => {
+                        state.end_drag(&self.x11, CURRENT_TIME)?;
+                    }
+                    MoveResizeDirection::SizeKeyboard | MoveResizeDirection::MoveKeyboard => {
+                        debug!("Keyboard move and resize is not supported");
+                    }
+                    _ => {
+                        state.begin_drag(
+                            &self.x11,
+                            window,
+                            direction,
+                            (x_root, y_root),
+                            CURRENT_TIME,
+                        )?;
+                    }
+                }
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,198 @@
+use coppe_common::{
+    event::Event,
+    key::{Key, KeyInput, Keysym},
+    window::ScreenId,
+};
+use log::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use x11rb::connection::Connection;
+use x11rb::errors::{ConnectionError, ReplyError};
+use x11rb::protocol::xproto::{
+    Allow, ConnectionExt as _, GrabMode, GrabStatus, KeyPressEvent, Timestamp,
+};
+use x11rb::CURRENT_TIME;
+
+use super::state::{SharedState, State};
+use crate::events::WmEvent;
+use crate::x11::X11Info;
+
+/// Inactivity timeout of keyboard grabs that do not specify one.
+pub const DEFAULT_GRAB_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest allowed inactivity timeout, so that a plugin can not hold the keyboard indefinitely.
+pub const MAX_GRAB_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longest time the keyboard stays frozen by a conditional key binding. The key press is replayed
+/// to the focused client afterwards, so that a slow or crashed plugin can not lock up the
+/// keyboard.
+pub const FROZEN_KEY_TIMEOUT: Duration = Duration::from_millis(500);
+
+static NEXT_SERIAL: AtomicU64 = AtomicU64::new(0);
+
+/// Active keyboard grab held on behalf of a plugin. The grab is released by the WM when Escape
+/// is pressed or when no key is pressed for `timeout`.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyboardGrab {
+    /// Distinguishes consecutive grabs, so that a watchdog of a released grab does not end a
+    /// newer one.
+    serial: u64,
+    timeout: Duration,
+    last_activity: Instant,
+}
+
+impl KeyboardGrab {
+    fn deadline(&self) -> Instant {
+        self.last_activity + self.timeout
+    }
+}
+
+impl State {
+    pub fn is_keyboard_grabbed(&self) -> bool {
+        self.keyboard_grab.is_some()
+    }
+
+    /// Actively grab the keyboard, so that all key events are reported as grabbed keys. Returns
+    /// serial of the grab, or `None` if the keyboard is grabbed by another X client. Grabbing
+    /// again replaces the current grab.
+    pub fn grab_keyboard(
+        &mut self,
+        x11: &X11Info,
+        timeout: Duration,
+    ) -> Result<Option<u64>, ReplyError> {
+        let root = match x11.screen(x11.screen_num as ScreenId) {
+            Some(screen) => screen.root,
+            None => return Ok(None),
+        };
+
+        let grab = x11
+            .conn
+            .grab_keyboard(false, root, CURRENT_TIME, GrabMode::ASYNC, GrabMode::ASYNC)?
+            .reply()?;
+        if grab.status != GrabStatus::SUCCESS {
+            warn!("Unable to grab keyboard: {:?}", grab.status);
+            return Ok(None);
+        }
+
+        let serial = NEXT_SERIAL.fetch_add(1, Ordering::Relaxed);
+        debug!("Keyboard grabbed with timeout {:?}", timeout);
+        self.keyboard_grab = Some(KeyboardGrab {
+            serial,
+            timeout: timeout.min(MAX_GRAB_TIMEOUT),
+            last_activity: Instant::now(),
+        });
+        Ok(Some(serial))
+    }
+
+    /// Release the keyboard grab. Returns `false` if the keyboard was not grabbed.
+    pub fn ungrab_keyboard(&mut self, x11: &X11Info) -> Result<bool, ConnectionError> {
+        if self.keyboard_grab.take().is_none() {
+            return Ok(false);
+        }
+
+        debug!("Keyboard ungrabbed");
+        x11.conn.ungrab_keyboard(CURRENT_TIME)?;
+        x11.conn.flush()?;
+        Ok(true)
+    }
+
+    /// Thaw the keyboard frozen by the key press at `time`, either consuming the key press or
+    /// replaying it to the focused client. Returns `false` if the keyboard was already released.
+    pub fn release_frozen_key(
+        &mut self,
+        x11: &X11Info,
+        time: Timestamp,
+        consumed: bool,
+    ) -> Result<bool, ConnectionError> {
+        if self.frozen_key != Some(time) {
+            return Ok(false);
+        }
+        self.frozen_key = None;
+
+        let mode = if consumed {
+            Allow::ASYNC_KEYBOARD
+        } else {
+            Allow::REPLAY_KEYBOARD
+        };
+        debug!("Releasing frozen keyboard with {:?}", mode);
+        x11.conn.allow_events(mode, time)?;
+        x11.conn.flush()?;
+        Ok(true)
+    }
+
+    /// Translate a key event during the keyboard grab. Key presses reset the inactivity timeout.
+    /// Returns `None` if Escape was pressed, in which case the grab is released.
+    pub fn grabbed_key(
+        &mut self,
+        x11: &X11Info,
+        event: &KeyPressEvent,
+        pressed: bool,
+    ) -> Result<Option<KeyInput>, ConnectionError> {
+        let key = Key::new(event.state.into(), event.detail.into());
+        let keysym = self.keymap.keysym(key.keycode, key.modmask);
+
+        if pressed {
+            if keysym == Keysym::Escape {
+                info!("Keyboard grab released with Escape");
+                self.ungrab_keyboard(x11)?;
+                return Ok(None);
+            }
+            if let Some(grab) = self.keyboard_grab.as_mut() {
+                grab.last_activity = Instant::now();
+            }
+        }
+
+        Ok(Some(KeyInput {
+            key,
+            keysym,
+            text: keysym.to_char(),
+        }))
+    }
+}
+
+/// Replay the key press at `time` to the focused client if plugins have not released the frozen
+/// keyboard within [FROZEN_KEY_TIMEOUT].
+pub fn spawn_frozen_key_watchdog(x11: X11Info, state: SharedState, time: Timestamp) {
+    thread::spawn(move || {
+        thread::sleep(FROZEN_KEY_TIMEOUT);
+        match state.write().release_frozen_key(&x11, time, false) {
+            Ok(true) => warn!(
+                "Key press was not handled by plugins within {:?}, replaying it",
+                FROZEN_KEY_TIMEOUT
+            ),
+            Ok(false) => {}
+            Err(e) => error!("Unable to release frozen keyboard: {:?}", e),
+        }
+    });
+}
+
+/// Release the grab with the given serial once it has been inactive for its timeout, notifying
+/// the plugin with `KeyboardGrabEnd`. The watchdog exits when the grab is released otherwise.
+pub fn spawn_grab_watchdog(
+    x11: X11Info,
+    state: SharedState,
+    tx: mpsc::Sender<WmEvent>,
+    serial: u64,
+) {
+    thread::spawn(move || loop {
+        let mut state = state.write();
+        let grab = match state.keyboard_grab {
+            Some(grab) if grab.serial == serial => grab,
+            _ => return,
+        };
+
+        let now = Instant::now();
+        if now < grab.deadline() {
+            drop(state);
+            thread::sleep(grab.deadline() - now);
+            continue;
+        }
+
+        warn!("Keyboard grab timed out after {:?}", grab.timeout);
+        if let Err(e) = state.ungrab_keyboard(&x11) {
+            error!("Unable to release keyboard grab: {:?}", e);
+        }
+        tx.send(WmEvent::new(Event::KeyboardGrabEnd, &state)).ok();
+        return;
+    });
+}
@@ -0,0 +1,562 @@
+use coppe_common::{
+    button::{Button, ButtonIndex},
+    key::{Key, Keycode, Keysym, ModMask},
+};
+use log::*;
+use std::collections::HashMap;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::cookie::VoidCookie;
+use x11rb::errors::{ConnectionError, ReplyError};
+use x11rb::protocol::xproto::*;
+use x11rb::rust_connection::RustConnection;
+
+use super::state::State;
+use crate::x11::X11Info;
+
+/// Cached keyboard mapping, used to translate between keycodes and keysyms.
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    min_keycode: u8,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<u32>,
+    /// Lock modifiers, which are ignored in bindings: `Lock` and modifiers that Num Lock and
+    /// Scroll Lock are mapped to.
+    locks: ModMask,
+}
+
+impl Keymap {
+    pub fn fetch(x11: &X11Info) -> Result<Self, ReplyError> {
+        let setup = x11.conn.setup();
+        let count = setup.max_keycode - setup.min_keycode + 1;
+        let keyboard = x11.conn.get_keyboard_mapping(setup.min_keycode, count)?;
+        let modifiers = x11.conn.get_modifier_mapping()?.reply()?;
+        let keyboard = keyboard.reply()?;
+
+        let mut keymap = Self {
+            min_keycode: setup.min_keycode,
+            keysyms_per_keycode: keyboard.keysyms_per_keycode,
+            keysyms: keyboard.keysyms,
+            locks: ModMask::LOCK,
+        };
+
+        // Modifier mapping lists keycodes of Shift, Lock, Control and Mod1 to Mod5 in order
+        let per_modifier = usize::from(modifiers.keycodes_per_modifier()).max(1);
+        for &keysym in &[Keysym::NumLock, Keysym::ScrollLock] {
+            let keycodes = keymap.keycodes(keysym);
+            for (i, row) in modifiers.keycodes.chunks(per_modifier).enumerate() {
+                if row.iter().any(|&code| keycodes.contains(&code.into())) {
+                    keymap.locks |= ModMask::from(1 << i);
+                }
+            }
+        }
+        debug!("Lock modifiers: {:?}", keymap.locks);
+
+        Ok(keymap)
+    }
+
+    /// Remove lock modifiers from the mask. Bindings are stored and matched without them.
+    pub fn strip_locks(&self, modmask: ModMask) -> ModMask {
+        modmask.without(self.locks)
+    }
+
+    /// All modifier masks a binding has to be grabbed with to work regardless of lock
+    /// modifiers. [ModMask::ANY] is grabbed as is.
+    pub fn lock_variants(&self, modmask: ModMask) -> Vec<ModMask> {
+        if modmask.contains(ModMask::ANY) {
+            return vec![ModMask::ANY];
+        }
+
+        let modmask = u16::from(self.strip_locks(modmask));
+        let locks = u16::from(self.locks);
+        // Iterate over all subsets of lock bits
+        let mut variants = vec![ModMask::from(modmask)];
+        let mut subset = locks;
+        while subset != 0 {
+            variants.push(ModMask::from(modmask | subset));
+            subset = (subset - 1) & locks;
+        }
+        variants
+    }
+
+    /// All keycodes that produce the keysym in any shift level or group.
+    pub fn keycodes(&self, keysym: Keysym) -> Vec<Keycode> {
+        let keysym = u32::from(keysym);
+        self.rows()
+            .filter(|(_, row)| row.contains(&keysym))
+            .map(|(keycode, _)| keycode)
+            .collect()
+    }
+
+    /// Distinct keysyms produced by the keycode, in order of shift levels.
+    pub fn keysyms(&self, keycode: Keycode) -> Vec<Keysym> {
+        let mut keysyms = Vec::new();
+        if let Some((_, row)) = self.rows().find(|&(code, _)| code == keycode) {
+            for &keysym in row {
+                if keysym != u32::from(Keysym::NoSymbol) && !keysyms.contains(&keysym.into()) {
+                    keysyms.push(keysym.into());
+                }
+            }
+        }
+        keysyms
+    }
+
+    /// Keysym produced by the keycode in the first group, choosing the shift level from Shift
+    /// and Caps Lock state.
+    pub fn keysym(&self, keycode: Keycode, modmask: ModMask) -> Keysym {
+        let row = match self.rows().find(|&(code, _)| code == keycode) {
+            Some((_, row)) => row,
+            None => return Keysym::NoSymbol,
+        };
+        let level = |i: usize| {
+            row.get(i)
+                .copied()
+                .filter(|&keysym| keysym != u32::from(Keysym::NoSymbol))
+        };
+        let lower = level(0);
+        let upper = level(1).or(lower);
+
+        // Caps Lock only affects letters, which have their uppercase variant on the second level
+        let letter =
+            lower.is_some_and(|keysym| (u32::from('a')..=u32::from('z')).contains(&keysym));
+        let shifted =
+            modmask.contains(ModMask::SHIFT) ^ (letter && modmask.contains(ModMask::LOCK));
+        let keysym = if shifted { upper } else { lower };
+        keysym.map_or(Keysym::NoSymbol, Keysym::from)
+    }
+
+    fn rows(&self) -> impl Iterator<Item = (Keycode, &[u32])> {
+        let min_keycode = self.min_keycode;
+        self.keysyms
+            .chunks(usize::from(self.keysyms_per_keycode.max(1)))
+            .enumerate()
+            .map(move |(i, row)| (Keycode::from((usize::from(min_keycode) + i) as u8), row))
+    }
+}
+
+/// Keycodes grabbed for a bound keysym.
+#[derive(Debug, Clone)]
+pub struct KeysymGrab {
+    keycodes: Vec<Keycode>,
+    /// Key presses freeze the keyboard until they are released with `AllowEvents`.
+    sync: bool,
+}
+
+pub type KeysymGrabs = HashMap<(ModMask, Keysym), KeysymGrab>;
+
+/// Key or button of a passive grab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GrabTarget {
+    Key(Keycode),
+    Button(ButtonIndex),
+}
+
+/// Bindings that share a passive grab.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrabCount {
+    bindings: u32,
+    /// Bindings that need the grab to freeze the keyboard.
+    sync: u32,
+}
+
+/// Passive grabs on root windows by target and modifiers without lock modifiers. X grabs are not
+/// reference counted, so a grab is shared by all bindings on the same combination and only
+/// released with the last one.
+pub type PassiveGrabs = HashMap<(Window, GrabTarget, ModMask), GrabCount>;
+
+impl State {
+    /// Grab the key on all screens regardless of lock modifiers. Fails with `Access` error if
+    /// another X client has already grabbed it. Synchronous grabs freeze the keyboard on key
+    /// press, see [State::is_sync_grabbed]. Every call adds a binding that has to be removed with
+    /// [State::ungrab_key].
+    pub fn grab_key(&mut self, x11: &X11Info, key: Key, sync: bool) -> Result<(), ReplyError> {
+        self.acquire_grabs(x11, &[GrabTarget::Key(key.keycode)], key.modmask, sync)
+    }
+
+    /// Remove a binding added with [State::grab_key] with the same `sync` flag.
+    pub fn ungrab_key(
+        &mut self,
+        x11: &X11Info,
+        key: Key,
+        sync: bool,
+    ) -> Result<(), ConnectionError> {
+        self.release_grabs(x11, &[GrabTarget::Key(key.keycode)], key.modmask, sync)
+    }
+
+    /// Grab the button on all screens regardless of lock modifiers. Button presses, releases and
+    /// motion until release are reported relative to the root window. Fails with `Access` error
+    /// if another X client has already grabbed it. Every call adds a binding that has to be
+    /// removed with [State::ungrab_button].
+    pub fn grab_button(&mut self, x11: &X11Info, button: Button) -> Result<(), ReplyError> {
+        self.acquire_grabs(
+            x11,
+            &[GrabTarget::Button(button.index)],
+            button.modmask,
+            false,
+        )
+    }
+
+    pub fn ungrab_button(&mut self, x11: &X11Info, button: Button) -> Result<(), ConnectionError> {
+        self.release_grabs(
+            x11,
+            &[GrabTarget::Button(button.index)],
+            button.modmask,
+            false,
+        )
+    }
+
+    /// Grab all keycodes that produce the keysym. The binding is grabbed again with the new
+    /// keycodes when the keyboard mapping changes. Nothing is grabbed if any of the keycodes is
+    /// already grabbed by another X client. Grabbing a bound keysym again can only make the grab
+    /// synchronous.
+    pub fn grab_keysym(
+        &mut self,
+        x11: &X11Info,
+        modmask: ModMask,
+        keysym: Keysym,
+        sync: bool,
+    ) -> Result<(), ReplyError> {
+        if let Some(grab) = self.keysym_grabs.get(&(modmask, keysym)).cloned() {
+            if sync && !grab.sync {
+                let targets = key_targets(&grab.keycodes);
+                // Synchronous grab is added first, so that the keys stay grabbed
+                self.acquire_grabs(x11, &targets, modmask, true)?;
+                self.release_grabs(x11, &targets, modmask, false)?;
+                if let Some(grab) = self.keysym_grabs.get_mut(&(modmask, keysym)) {
+                    grab.sync = true;
+                }
+            }
+            return Ok(());
+        }
+
+        let keycodes = self.keymap.keycodes(keysym);
+        if keycodes.is_empty() {
+            warn!("Keysym {:?} is not present in the keyboard mapping", keysym);
+        }
+        self.acquire_grabs(x11, &key_targets(&keycodes), modmask, sync)?;
+        self.keysym_grabs
+            .insert((modmask, keysym), KeysymGrab { keycodes, sync });
+        Ok(())
+    }
+
+    pub fn ungrab_keysym(
+        &mut self,
+        x11: &X11Info,
+        modmask: ModMask,
+        keysym: Keysym,
+    ) -> Result<(), ConnectionError> {
+        if let Some(grab) = self.keysym_grabs.remove(&(modmask, keysym)) {
+            self.release_grabs(x11, &key_targets(&grab.keycodes), modmask, grab.sync)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the key press has frozen the keyboard with a synchronous grab. The keyboard stays
+    /// frozen until the press is released or replayed to the focused client with `AllowEvents`.
+    pub fn is_sync_grabbed(&self, root: Window, modmask: ModMask, keycode: Keycode) -> bool {
+        let modmask = self.keymap.strip_locks(key_modifiers(modmask));
+        [modmask, ModMask::ANY].iter().any(|&modmask| {
+            self.passive_grabs
+                .get(&(root, GrabTarget::Key(keycode), modmask))
+                .is_some_and(|count| count.sync > 0)
+        })
+    }
+
+    /// Bound keysyms produced by the key press. Lock modifiers and pointer buttons are ignored.
+    pub fn bound_keysyms(&self, modmask: ModMask, keycode: Keycode) -> Vec<Keysym> {
+        let modmask = self.keymap.strip_locks(key_modifiers(modmask));
+        self.keymap
+            .keysyms(keycode)
+            .into_iter()
+            .filter(|&keysym| {
+                self.keysym_grabs.contains_key(&(modmask, keysym))
+                    || self.keysym_grabs.contains_key(&(ModMask::ANY, keysym))
+            })
+            .collect()
+    }
+
+    /// Re-read the keyboard mapping and move keysym grabs to the new keycodes. All grabs are
+    /// replaced if lock modifiers have changed.
+    pub fn update_keymap(&mut self, x11: &X11Info) -> Result<(), ReplyError> {
+        let old = std::mem::replace(&mut self.keymap, Keymap::fetch(x11)?);
+        if old.locks != self.keymap.locks {
+            debug!("Lock modifiers changed, grabbing all bindings again");
+            for &grab in self.passive_grabs.keys() {
+                send_ungrab(x11, &old, grab)?;
+            }
+            let mut requests = Vec::new();
+            for (&grab, count) in &self.passive_grabs {
+                requests.push((grab, send_grab(x11, &self.keymap, grab, count.sync > 0)?));
+            }
+            for (grab, cookies) in requests {
+                if let Err(e) = check_all(cookies) {
+                    warn!("Unable to grab {:?} again: {:?}", grab, e);
+                }
+            }
+        }
+
+        let bindings: Vec<_> = self
+            .keysym_grabs
+            .iter()
+            .map(|(&binding, grab)| (binding, grab.clone()))
+            .collect();
+        for ((modmask, keysym), grab) in bindings {
+            let new_keycodes = self.keymap.keycodes(keysym);
+            if grab.keycodes != new_keycodes {
+                debug!(
+                    "Keysym {:?} moved from {:?} to {:?}",
+                    keysym, grab.keycodes, new_keycodes
+                );
+                self.release_grabs(x11, &key_targets(&grab.keycodes), modmask, grab.sync)?;
+                let targets = key_targets(&new_keycodes);
+                // Binding stays without keycodes if grabbing fails, so that only grabs that are
+                // held are released later. Grabbing is tried again on the next mapping change.
+                let keycodes = match self.acquire_grabs(x11, &targets, modmask, grab.sync) {
+                    Ok(()) => new_keycodes,
+                    Err(e) => {
+                        warn!("Unable to grab keysym {:?} again: {:?}", keysym, e);
+                        Vec::new()
+                    }
+                };
+                if let Some(grab) = self.keysym_grabs.get_mut(&(modmask, keysym)) {
+                    grab.keycodes = keycodes;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a binding to the targets on all screens, grabbing the ones that are not grabbed yet.
+    /// Nothing is changed if any of the targets is already grabbed by another X client, only
+    /// grabs created by this call are released in that case.
+    fn acquire_grabs(
+        &mut self,
+        x11: &X11Info,
+        targets: &[GrabTarget],
+        modmask: ModMask,
+        sync: bool,
+    ) -> Result<(), ReplyError> {
+        let mut requests = Vec::new();
+        for root in x11.roots() {
+            for &target in targets {
+                let grab = (root, target, modmask);
+                let count = self.passive_grabs.get(&grab).copied().unwrap_or_default();
+                let created = count.bindings == 0;
+                // Grabbing again replaces the mode of the own grab
+                if created || (sync && count.sync == 0) {
+                    requests.push((grab, created, send_grab(x11, &self.keymap, grab, sync)?));
+                }
+            }
+        }
+
+        let mut result = Ok(());
+        let mut changed = Vec::new();
+        for (grab, created, cookies) in requests {
+            match check_all(cookies) {
+                Ok(()) => changed.push((grab, created)),
+                Err(e) => {
+                    // Variants that were grabbed before the failure are released
+                    if created {
+                        send_ungrab(x11, &self.keymap, grab)?;
+                    }
+                    result = result.and(Err(e));
+                }
+            }
+        }
+        if let Err(e) = result {
+            for (grab, created) in changed {
+                if created {
+                    send_ungrab(x11, &self.keymap, grab)?;
+                } else {
+                    send_grab(x11, &self.keymap, grab, false)?
+                        .into_iter()
+                        .for_each(VoidCookie::ignore_error);
+                }
+            }
+            return Err(e);
+        }
+
+        for root in x11.roots() {
+            for &target in targets {
+                let count = self
+                    .passive_grabs
+                    .entry((root, target, modmask))
+                    .or_default();
+                count.bindings += 1;
+                count.sync += u32::from(sync);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a binding from the targets on all screens, releasing grabs that are no longer used.
+    fn release_grabs(
+        &mut self,
+        x11: &X11Info,
+        targets: &[GrabTarget],
+        modmask: ModMask,
+        sync: bool,
+    ) -> Result<(), ConnectionError> {
+        for root in x11.roots() {
+            for &target in targets {
+                let grab = (root, target, modmask);
+                let count = match self.passive_grabs.get_mut(&grab) {
+                    Some(count) => count,
+                    None => continue,
+                };
+                count.bindings = count.bindings.saturating_sub(1);
+                if sync {
+                    count.sync = count.sync.saturating_sub(1);
+                }
+
+                if count.bindings == 0 {
+                    self.passive_grabs.remove(&grab);
+                    send_ungrab(x11, &self.keymap, grab)?;
+                } else if sync && count.sync == 0 {
+                    // Remaining bindings do not freeze the keyboard
+                    send_grab(x11, &self.keymap, grab, false)?
+                        .into_iter()
+                        .for_each(VoidCookie::ignore_error);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Modifiers of a key event state. The state also contains pointer buttons, which do not affect
+/// key grabs.
+pub fn key_modifiers(state: ModMask) -> ModMask {
+    ModMask::from(u16::from(state) & 0xff)
+}
+
+fn key_targets(keycodes: &[Keycode]) -> Vec<GrabTarget> {
+    keycodes.iter().copied().map(GrabTarget::Key).collect()
+}
+
+/// Grab the target on the root window with every combination of lock modifiers. Grabbing again
+/// replaces the mode of the grab. Keys are grabbed with `owner_events`, buttons report presses,
+/// releases and motion until release relative to the root window.
+fn send_grab<'c>(
+    x11: &'c X11Info,
+    keymap: &Keymap,
+    (root, target, modmask): (Window, GrabTarget, ModMask),
+    sync: bool,
+) -> Result<Vec<VoidCookie<'c, RustConnection>>, ConnectionError> {
+    let keyboard_mode = if sync {
+        GrabMode::SYNC
+    } else {
+        GrabMode::ASYNC
+    };
+    let mask = EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE | EventMask::POINTER_MOTION;
+
+    let mut cookies = Vec::new();
+    for modmask in keymap.lock_variants(modmask) {
+        cookies.push(match target {
+            GrabTarget::Key(keycode) => {
+                x11.conn
+                    .grab_key(true, root, modmask, keycode, GrabMode::ASYNC, keyboard_mode)?
+            }
+            GrabTarget::Button(index) => x11.conn.grab_button(
+                false,
+                root,
+                u32::from(mask) as u16,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                x11rb::NONE,
+                x11rb::NONE,
+                u8::from(index).into(),
+                modmask,
+            )?,
+        });
+    }
+    Ok(cookies)
+}
+
+fn send_ungrab(
+    x11: &X11Info,
+    keymap: &Keymap,
+    (root, target, modmask): (Window, GrabTarget, ModMask),
+) -> Result<(), ConnectionError> {
+    for modmask in keymap.lock_variants(modmask) {
+        match target {
+            GrabTarget::Key(keycode) => x11.conn.ungrab_key(keycode, root, modmask)?,
+            GrabTarget::Button(index) => {
+                x11.conn
+                    .ungrab_button(u8::from(index).into(), root, modmask)?
+            }
+        };
+    }
+    Ok(())
+}
+
+/// Wait for all requests and return the first error. Every cookie is checked, so that the
+/// remaining errors are not delivered to the event loop.
+fn check_all<C: RequestConnection>(cookies: Vec<VoidCookie<'_, C>>) -> Result<(), ReplyError> {
+    cookies
+        .into_iter()
+        .map(VoidCookie::check)
+        .fold(Ok(()), Result::and)
+}
+
+#[cfg(test)]
+mod tests {
+    use coppe_common::key::{Keysym, ModMask};
+
+    use super::{GrabCount, GrabTarget, Keymap, KeysymGrab};
+    use crate::wm::State;
+
+    const KEYCODE: u8 = 10;
+
+    /// State with keysym `a` bound to the only key with Mod1, grabbed synchronously.
+    fn state_with_binding() -> State {
+        let a = Keysym::from(u32::from('a'));
+        let mut state = State {
+            keymap: Keymap {
+                min_keycode: KEYCODE,
+                keysyms_per_keycode: 2,
+                keysyms: vec![u32::from('a'), u32::from('A')],
+                locks: ModMask::LOCK,
+            },
+            ..State::default()
+        };
+        state.keysym_grabs.insert(
+            (ModMask::M1, a),
+            KeysymGrab {
+                keycodes: vec![KEYCODE.into()],
+                sync: true,
+            },
+        );
+        state.passive_grabs.insert(
+            (1, GrabTarget::Key(KEYCODE.into()), ModMask::M1),
+            GrabCount {
+                bindings: 1,
+                sync: 1,
+            },
+        );
+        state
+    }
+
+    #[test]
+    fn bound_keysyms() {
+        let state = state_with_binding();
+        let a = Keysym::from(u32::from('a'));
+        let keycode = KEYCODE.into();
+        assert_eq!(state.bound_keysyms(ModMask::M1, keycode), vec![a]);
+        assert_eq!(
+            state.bound_keysyms(ModMask::M1 | ModMask::LOCK, keycode),
+            vec![a]
+        );
+        assert!(state.bound_keysyms(ModMask::M4, keycode).is_empty());
+        assert!(state.bound_keysyms(ModMask::M1, 11.into()).is_empty());
+    }
+
+    #[test]
+    fn pointer_buttons_are_ignored() {
+        let state = state_with_binding();
+        let a = Keysym::from(u32::from('a'));
+        let keycode = KEYCODE.into();
+        let modmask = ModMask::M1 | ModMask::BUTTON1 | ModMask::BUTTON3;
+        assert_eq!(state.bound_keysyms(modmask, keycode), vec![a]);
+        assert!(state.is_sync_grabbed(1, modmask, keycode));
+    }
+}
@@ -1,17 +1,35 @@
+use coppe_common::{event::Event, window::Geometry};
 use log::*;
 use std::{collections::HashSet, sync::mpsc};
 use x11rb::connection::Connection;
-use x11rb::errors::{ReplyError, ReplyOrIdError};
+use x11rb::errors::{ConnectionError, ReplyError, ReplyOrIdError};
 use x11rb::protocol::xproto::*;
+use x11rb::wrapper::ConnectionExt as _;
 use x11rb::x11_utils::X11Error;
+use x11rb::CURRENT_TIME;
 
+mod click;
+mod client;
+mod drag;
+mod ewmh;
+mod focus;
 mod handler;
+mod keyboard;
+mod keymap;
+mod monitor;
+mod state;
+mod workspace;
 
 use crate::events::WmEvent;
 use crate::x11::X11Info;
 
+pub use client::{Client, WmState};
+pub use keyboard::{spawn_grab_watchdog, DEFAULT_GRAB_TIMEOUT};
+pub use state::{SharedState, State};
+
 pub struct WindowManager {
     x11: X11Info,
+    state: SharedState,
     pub pending_expose: HashSet<Window>,
     tx: mpsc::Sender<WmEvent>,
 }
@@ -19,44 +37,31 @@ pub struct WindowManager {
 impl WindowManager {
     // TODO: Restructure
     pub fn init(x11: X11Info, tx: mpsc::Sender<WmEvent>) -> Result<Self, ReplyOrIdError> {
-        let screen = &x11.conn.setup().roots[x11.screen_num];
-        // Try to become the window manager. This causes an error if there is already another WM.
+        // Try to become the window manager on every screen. This causes an error if there is
+        // already another WM.
         let change = ChangeWindowAttributesAux::default().event_mask(
             EventMask::SUBSTRUCTURE_REDIRECT
                 | EventMask::SUBSTRUCTURE_NOTIFY
-                | EventMask::ENTER_WINDOW,
+                | EventMask::ENTER_WINDOW
+                | EventMask::FOCUS_CHANGE,
         );
 
-        let res = x11
-            .conn
-            .change_window_attributes(screen.root, &change)?
-            .check();
-        match res {
-            Err(ReplyError::X11Error(X11Error {
-                error_kind: x11rb::protocol::ErrorKind::Access,
-                ..
-            })) => {
-                error!("Another WM is already running");
-                std::process::exit(1)
-            }
-            Err(e) => return Err(e.into()),
-            _ => {
-                x11.conn.grab_button(
-                    true,
-                    screen.root,
-                    u32::from(
-                        EventMask::BUTTON_RELEASE
-                            | EventMask::BUTTON_PRESS
-                            | EventMask::BUTTON3_MOTION
-                            | EventMask::BUTTON1_MOTION,
-                    ) as u16,
-                    GrabMode::ASYNC,
-                    GrabMode::ASYNC,
-                    screen.root,
-                    0u16,
-                    ButtonIndex::M1,
-                    ModMask::M4,
-                )?;
+        let keymap = keymap::Keymap::fetch(&x11)?;
+        for root in x11.roots() {
+            let res = x11.conn.change_window_attributes(root, &change)?.check();
+            match res {
+                Err(ReplyError::X11Error(X11Error {
+                    error_kind: x11rb::protocol::ErrorKind::Access,
+                    ..
+                })) => {
+                    error!("Another WM is already running");
+                    std::process::exit(1)
+                }
+                Err(e) => return Err(e.into()),
+                _ => {
+                    ewmh::init(&x11, root)?;
+                    monitor::init(&x11, root)?;
+                }
             }
         }
 
@@ -65,43 +70,68 @@ impl WindowManager {
         //    ChangeKeyboardControlAux::new().auto_repeat_mode(AutoRepeatMode::OFF);
         //conn.change_keyboard_control(&keyboard_control)?;
 
+        let monitors = monitor::query_all(&x11)?;
+        let state = SharedState::default();
+        {
+            let mut state = state.write();
+            state.publish_client_list(&x11)?;
+            state.publish_active_window(&x11)?;
+            state.update_monitors(monitors);
+            state.keymap = keymap;
+            if let Err(e) = state.grab_drag_buttons(&x11) {
+                warn!("Unable to grab buttons for window dragging: {:?}", e);
+                state.drag_config.enabled = false;
+            }
+            let workspaces = workspace::DEFAULT_WORKSPACES
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            state.set_workspaces(&x11, workspaces)?;
+        }
+
         Ok(WindowManager {
             x11,
+            state,
             pending_expose: HashSet::default(),
             tx,
         })
     }
 
-    pub fn run(&mut self) -> Result<(), ReplyError> {
-        self.scan_windows().unwrap();
+    pub fn state(&self) -> SharedState {
+        self.state.clone()
+    }
+
+    pub fn run(&mut self) -> Result<(), ReplyOrIdError> {
+        self.scan_windows()?;
 
         loop {
-            self.refresh().unwrap();
-            self.x11.conn.flush().unwrap();
+            self.refresh()?;
+            self.x11.conn.flush()?;
 
             // Handle as many events as possible before refresh, then wait again
             let mut event_opt = self.x11.conn.wait_for_event().ok();
             while let Some(event) = event_opt {
-                self.handle_event(event).unwrap();
-                event_opt = self.x11.conn.poll_for_event().unwrap();
+                self.handle_event(event)?;
+                event_opt = self.x11.conn.poll_for_event()?;
             }
         }
     }
 
-    pub fn scan_windows(&self) -> Result<(), ReplyOrIdError> {
-        let screen = &self.x11.conn.setup().roots[self.x11.screen_num];
-        let tree_reply = self.x11.conn.query_tree(screen.root)?.reply()?;
-
-        let mut cookies = Vec::with_capacity(tree_reply.children.len());
-        for win in tree_reply.children {
-            let attr = self.x11.conn.get_window_attributes(win)?;
-            let geom = self.x11.conn.get_geometry(win)?;
-            cookies.push((win, attr, geom));
+    pub fn scan_windows(&mut self) -> Result<(), ReplyOrIdError> {
+        let conn = self.x11.conn.clone();
+        let mut cookies = Vec::new();
+        for root in self.x11.roots() {
+            let tree_reply = conn.query_tree(root)?.reply()?;
+            for win in tree_reply.children {
+                let attr = conn.get_window_attributes(win)?;
+                let geom = conn.get_geometry(win)?;
+                cookies.push((win, attr, geom));
+            }
         }
         for (win, attr, geom) in cookies {
             if let (Ok(attr), Ok(geom)) = (attr.reply(), geom.reply()) {
                 if !attr.override_redirect && attr.map_state != MapState::UNMAPPED {
-                    self.manage_window(win, &geom)?;
+                    ignore_destroyed(win, self.manage_window(win, &geom))?;
                 }
             }
         }
@@ -110,7 +140,7 @@ impl WindowManager {
     }
 
     pub fn manage_window(
-        &self,
+        &mut self,
         win: Window,
         geom: &GetGeometryReply,
     ) -> Result<(), ReplyOrIdError> {
@@ -122,16 +152,240 @@ impl WindowManager {
             .width(u32::from(geom.width))
             .height(u32::from(geom.height));
 
+        let change = ChangeWindowAttributesAux::default().event_mask(
+            EventMask::PROPERTY_CHANGE
+                | EventMask::ENTER_WINDOW
+                | EventMask::LEAVE_WINDOW
+                | EventMask::FOCUS_CHANGE,
+        );
+        self.x11.conn.change_window_attributes(win, &change)?;
+
+        let is_new = !self.state.read().clients.contains_key(&win);
+        if is_new {
+            let screen = self.x11.screen_of(geom.root).unwrap_or_default();
+            let geometry = Geometry {
+                x: geom.x,
+                y: geom.y,
+                width: geom.width,
+                height: geom.height,
+            };
+            let requested_workspace = self
+                .x11
+                .conn
+                .get_property(
+                    false,
+                    win,
+                    self.x11.atoms._NET_WM_DESKTOP,
+                    AtomEnum::CARDINAL,
+                    0,
+                    1,
+                )?
+                .reply()?
+                .value32()
+                .and_then(|mut value| value.next());
+
+            let mut client = Client::fetch(&self.x11, win, screen, geometry)?;
+            let mut state = self.state.write();
+            client.workspace = requested_workspace
+                .filter(|&workspace| state.is_valid_workspace(workspace))
+                .unwrap_or(state.current_workspace);
+            state.clients.insert(win, client);
+        }
+
         self.x11.conn.configure_window(win, &aux)?;
-        self.x11.conn.map_window(win)?;
 
+        let mut state = self.state.write();
+        let current_workspace = state.current_workspace;
+        let client = match state.clients.get_mut(&win) {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+        let was_managed = client.state != WmState::Withdrawn;
+        // New clients keep the workspace they requested, withdrawn clients that map themselves
+        // again show up on the current workspace
+        if !was_managed && !is_new {
+            client.workspace = current_workspace;
+        }
+        client.state = WmState::Normal;
+
+        // Windows on hidden workspaces stay unmapped until the workspace is shown
+        if client.is_visible(current_workspace) {
+            self.x11.conn.map_window(win)?;
+        }
+        self.set_wm_state(win, WmState::Normal)?;
+        workspace::publish_window_workspace(&self.x11, client)?;
+
+        if !was_managed {
+            let screen = client.screen;
+            state.add_to_lists(win);
+            state.publish_client_list(&self.x11)?;
+            self.emit(&state, Event::WindowAdd(win, screen));
+        }
+        // Clients may request fullscreen or maximized state before they are mapped
+        if is_new {
+            state.apply_window_state(&self.x11, win)?;
+        }
+
+        Ok(())
+    }
+
+    /// Mark client as withdrawn. The client is still tracked until it is destroyed, so that it
+    /// can be managed again if it maps itself later.
+    pub fn withdraw_window(&mut self, win: Window) -> Result<(), ConnectionError> {
+        let mut state = self.state.write();
+        if let Some(client) = state.clients.get_mut(&win) {
+            if client.state == WmState::Withdrawn {
+                return Ok(());
+            }
+            info!("Window {:?} withdrawn", win);
+            client.state = WmState::Withdrawn;
+            client.ignore_unmap = 0;
+            self.set_wm_state(win, WmState::Withdrawn)?;
+            // EWMH: the WM should remove `_NET_WM_STATE` when the window is withdrawn
+            self.x11
+                .conn
+                .delete_property(win, self.x11.atoms._NET_WM_STATE)?;
+            self.x11
+                .conn
+                .delete_property(win, self.x11.atoms._NET_WM_DESKTOP)?;
+            let screen = client.screen;
+            self.unlist_client(&mut state, win)?;
+            self.emit(&state, Event::WindowRemove(win, screen));
+        }
+        Ok(())
+    }
+
+    /// Forget about the destroyed window.
+    pub fn unmanage_window(&mut self, win: Window) -> Result<(), ConnectionError> {
+        let mut state = self.state.write();
+        let removed = state
+            .clients
+            .get(&win)
+            .filter(|client| client.state != WmState::Withdrawn)
+            .map(|client| WmEvent::new(Event::WindowRemove(win, client.screen), &state));
+        if state.clients.remove(&win).is_some() {
+            info!("Unmanaging window {:?}", win);
+            if let Some(event) = removed {
+                self.unlist_client(&mut state, win)?;
+                self.send(event);
+            }
+        }
+        Ok(())
+    }
+
+    /// Map iconified window back.
+    pub fn deiconify_window(&mut self, win: Window) -> Result<(), ConnectionError> {
+        let mut state = self.state.write();
+        let current_workspace = state.current_workspace;
+        if let Some(client) = state.clients.get_mut(&win) {
+            if client.state == WmState::Iconic {
+                client.state = WmState::Normal;
+                if client.is_visible(current_workspace) {
+                    self.x11.conn.map_window(win)?;
+                }
+                self.set_wm_state(win, WmState::Normal)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Raise and focus the window.
+    pub fn focus_window(&self, win: Window) -> Result<(), ConnectionError> {
+        let mut state = self.state.write();
+        if state.raise_window(&self.x11, win)? {
+            state.focus(&self.x11, win, CURRENT_TIME)?;
+        }
+        Ok(())
+    }
+
+    /// Ask the client to close gracefully, or kill it if it does not support `WM_DELETE_WINDOW`.
+    pub fn close_window(&self, win: Window) -> Result<(), ConnectionError> {
+        let supports_delete = self
+            .state
+            .read()
+            .clients
+            .get(&win)
+            .map(|client| client.protocols.contains(&self.x11.atoms.WM_DELETE_WINDOW));
+
+        match supports_delete {
+            Some(true) => {
+                self.x11
+                    .send_protocol(win, self.x11.atoms.WM_DELETE_WINDOW, CURRENT_TIME)?;
+            }
+            Some(false) => {
+                self.x11.conn.kill_client(win)?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn unlist_client(&self, state: &mut State, win: Window) -> Result<(), ConnectionError> {
+        let was_focused = state.focused == Some(win);
+        state.remove_from_lists(win);
+        state.publish_client_list(&self.x11)?;
+        if was_focused {
+            state.publish_active_window(&self.x11)?;
+        }
+        Ok(())
+    }
+
+    /// Unmap a managed window without treating the resulting `UnmapNotify` as client withdrawal.
+    pub fn unmap_window(&mut self, win: Window, state: WmState) -> Result<(), ConnectionError> {
+        let mut shared_state = self.state.write();
+        let current_workspace = shared_state.current_workspace;
+        if let Some(client) = shared_state.clients.get_mut(&win) {
+            if client.is_visible(current_workspace) {
+                client.ignore_unmap += 1;
+                self.x11.conn.unmap_window(win)?;
+            }
+            client.state = state;
+            self.set_wm_state(win, state)?;
+        }
+        Ok(())
+    }
+
+    fn set_wm_state(&self, win: Window, state: WmState) -> Result<(), ConnectionError> {
+        self.x11.conn.change_property32(
+            PropMode::REPLACE,
+            win,
+            self.x11.atoms.WM_STATE,
+            self.x11.atoms.WM_STATE,
+            &[state as u32, x11rb::NONE],
+        )?;
         Ok(())
     }
 
+    /// Dispatch the event to the runner. The window the event concerns is captured from the
+    /// state, so it must be emitted before the window is forgotten.
+    fn emit(&self, state: &State, event: Event) {
+        self.send(WmEvent::new(event, state));
+    }
+
+    fn send(&self, event: WmEvent) {
+        if self.tx.send(event).is_err() {
+            warn!("Unable to dispatch event, runner is not running");
+        }
+    }
+
     pub fn refresh(&mut self) -> Result<(), ReplyError> {
         while let Some(&win) = self.pending_expose.iter().next() {
             self.pending_expose.remove(&win);
         }
+        let mut state = self.state.write();
+        state.apply_drag(&self.x11)?;
+        state.update_click_grabs(&self.x11)?;
         Ok(())
     }
 }
+
+/// Treat failure to manage a window that was destroyed in the meantime as success.
+fn ignore_destroyed(win: Window, result: Result<(), ReplyOrIdError>) -> Result<(), ReplyOrIdError> {
+    match result {
+        Err(ReplyOrIdError::X11Error(e)) if e.error_kind == x11rb::protocol::ErrorKind::Window => {
+            debug!("Window {:?} destroyed before it was managed", win);
+            Ok(())
+        }
+        result => result,
+    }
+}
@@ -0,0 +1,155 @@
+use coppe_common::{
+    event::Event,
+    monitor::{Monitor, MonitorList},
+    window::{Geometry, ScreenId},
+};
+use log::*;
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::randr::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{ConnectionExt as _, Screen, Window};
+
+use super::client::Client;
+use super::state::State;
+use crate::x11::X11Info;
+
+/// Subscribe to screen configuration changes, if RandR is available.
+pub fn init(x11: &X11Info, root: Window) -> Result<(), ReplyError> {
+    if x11.has_randr(1, 2) {
+        x11.conn.randr_select_input(
+            root,
+            randr::NotifyMask::SCREEN_CHANGE | randr::NotifyMask::CRTC_CHANGE,
+        )?;
+    } else {
+        warn!("RandR 1.2 is not available, monitor changes will not be tracked");
+    }
+    Ok(())
+}
+
+/// Query monitor layout of all screens.
+pub fn query_all(x11: &X11Info) -> Result<Vec<MonitorList>, ReplyError> {
+    x11.conn
+        .setup()
+        .roots
+        .iter()
+        .map(|screen| query(x11, screen))
+        .collect()
+}
+
+/// Query monitor layout. Uses RandR 1.5 monitors if available, falls back to active CRTCs and
+/// then to the whole root window.
+pub fn query(x11: &X11Info, screen: &Screen) -> Result<MonitorList, ReplyError> {
+    let mut list = MonitorList::new();
+
+    if x11.has_randr(1, 5) {
+        let reply = x11.conn.randr_get_monitors(screen.root, true)?.reply()?;
+        for monitor in reply.monitors {
+            list.push(Monitor {
+                geometry: Geometry {
+                    x: monitor.x,
+                    y: monitor.y,
+                    width: monitor.width,
+                    height: monitor.height,
+                },
+                primary: monitor.primary,
+            });
+        }
+    } else if x11.has_randr(1, 2) {
+        // Cheaper query of resources without polling outputs and the primary output only exist
+        // since RandR 1.3
+        let (crtcs, timestamp, primary) = if x11.has_randr(1, 3) {
+            let resources = x11
+                .conn
+                .randr_get_screen_resources_current(screen.root)?
+                .reply()?;
+            let primary = x11.conn.randr_get_output_primary(screen.root)?.reply()?;
+            (resources.crtcs, resources.config_timestamp, primary.output)
+        } else {
+            let resources = x11.conn.randr_get_screen_resources(screen.root)?.reply()?;
+            (resources.crtcs, resources.config_timestamp, x11rb::NONE)
+        };
+
+        let mut cookies = Vec::with_capacity(crtcs.len());
+        for &crtc in &crtcs {
+            cookies.push(x11.conn.randr_get_crtc_info(crtc, timestamp)?);
+        }
+        for cookie in cookies {
+            let crtc = cookie.reply()?;
+            if crtc.mode == 0 || crtc.width == 0 || crtc.height == 0 {
+                continue;
+            }
+            let geometry = Geometry {
+                x: crtc.x,
+                y: crtc.y,
+                width: crtc.width,
+                height: crtc.height,
+            };
+            let primary = primary != x11rb::NONE && crtc.outputs.contains(&primary);
+            // Mirrored outputs on separate CRTCs report the same geometry
+            if !list.iter().any(|monitor| monitor.geometry == geometry) {
+                list.push(Monitor { geometry, primary });
+            }
+        }
+    }
+
+    if list.is_empty() {
+        // Screen size in the connection setup is not updated when the screen is resized
+        let root = x11.conn.get_geometry(screen.root)?.reply()?;
+        list.push(Monitor {
+            geometry: Geometry {
+                x: 0,
+                y: 0,
+                width: root.width,
+                height: root.height,
+            },
+            primary: true,
+        });
+    }
+
+    Ok(list)
+}
+
+impl State {
+    /// Monitor that contains the center of the client and its index in the monitor list of the
+    /// client's screen.
+    pub fn monitor_of(&self, client: &Client) -> Option<(usize, &Monitor)> {
+        let geometry = client.geometry;
+        let x = i32::from(geometry.x) + i32::from(geometry.width) / 2;
+        let y = i32::from(geometry.y) + i32::from(geometry.height) / 2;
+
+        self.monitors
+            .get(client.screen as usize)?
+            .iter()
+            .enumerate()
+            .find(|(_, monitor)| {
+                let monitor = monitor.geometry;
+                let left = i32::from(monitor.x);
+                let top = i32::from(monitor.y);
+                (left..left + i32::from(monitor.width)).contains(&x)
+                    && (top..top + i32::from(monitor.height)).contains(&y)
+            })
+    }
+
+    /// Replace monitor layout of all screens with the queried one, see [query_all]. Returns
+    /// events that should be dispatched to plugins for screens whose layout has changed.
+    pub fn update_monitors(&mut self, layouts: Vec<MonitorList>) -> Vec<Event> {
+        let mut events = Vec::new();
+        for (i, monitors) in layouts.into_iter().enumerate() {
+            if self.monitors.get(i) == Some(&monitors) {
+                continue;
+            }
+
+            info!("Monitor layout of screen {} changed: {:?}", i, monitors);
+            if i < self.monitors.len() {
+                self.monitors[i] = monitors;
+            } else {
+                self.monitors.push(monitors);
+            }
+            events.push(Event::MonitorsChanged {
+                screen: i as ScreenId,
+                monitors,
+            });
+        }
+        events
+    }
+}
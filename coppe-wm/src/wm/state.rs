@@ -0,0 +1,87 @@
+use coppe_common::{
+    monitor::MonitorList,
+    window::{ClientRequestKind, FocusPolicy, WorkspaceId},
+};
+use parking_lot::RwLock;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use x11rb::protocol::xproto::{Timestamp, Window};
+
+use super::click::ClickConfig;
+use super::client::Client;
+use super::drag::{Drag, DragConfig};
+use super::keyboard::KeyboardGrab;
+use super::keymap::{Keymap, KeysymGrabs, PassiveGrabs};
+
+pub type SharedState = Arc<RwLock<State>>;
+
+/// WM state, shared between WM event loop and plugin runner.
+#[derive(Debug, Default)]
+pub struct State {
+    /// Registry of all managed windows, including withdrawn ones that are not yet destroyed.
+    pub clients: HashMap<Window, Client>,
+    /// Non-withdrawn clients in order of mapping.
+    pub client_list: Vec<Window>,
+    /// Non-withdrawn clients in bottom-to-top stacking order.
+    pub stacking: Vec<Window>,
+    pub focused: Option<Window>,
+    /// Focused client as last reported to plugins with `WindowFocus` event.
+    pub reported_focus: Option<Window>,
+    pub focus_policy: FocusPolicy,
+    pub click_config: ClickConfig,
+    /// Unfocused clients with a button grab for click-to-focus.
+    pub click_grabs: HashSet<Window>,
+    /// Interactive move or resize in progress.
+    pub drag: Option<Drag>,
+    pub drag_config: DragConfig,
+    /// Workspace names. There is always at least one workspace after WM initialization.
+    pub workspaces: Vec<String>,
+    pub current_workspace: WorkspaceId,
+    /// Monitor layout of each screen, indexed by screen number.
+    pub monitors: Vec<MonitorList>,
+    pub keymap: Keymap,
+    /// Keysym bindings of plugins with the keycodes they are currently grabbed on.
+    pub keysym_grabs: KeysymGrabs,
+    /// Passive key and button grabs of key bindings, plugin button subscriptions and drag
+    /// buttons.
+    pub passive_grabs: PassiveGrabs,
+    /// Keyboard grab held on behalf of a plugin.
+    pub keyboard_grab: Option<KeyboardGrab>,
+    /// Time of the key press that froze the keyboard with a synchronous grab, until the key press
+    /// is consumed or replayed.
+    pub frozen_key: Option<Timestamp>,
+    /// Client requests that are handled by plugins instead of the WM.
+    pub intercepted_requests: HashSet<ClientRequestKind>,
+}
+
+impl State {
+    /// Add client to client lists, placing it on top of the stack.
+    pub fn add_to_lists(&mut self, win: Window) {
+        if !self.client_list.contains(&win) {
+            self.client_list.push(win);
+            self.stacking.push(win);
+        }
+    }
+
+    /// Remove client from client lists and drop focus if it was focused.
+    pub fn remove_from_lists(&mut self, win: Window) {
+        self.client_list.retain(|&w| w != win);
+        self.stacking.retain(|&w| w != win);
+        if self.focused == Some(win) {
+            self.focused = None;
+        }
+    }
+
+    /// Move client to the top of the stack. Returns `false` if window is not listed.
+    pub fn raise(&mut self, win: Window) -> bool {
+        if let Some(pos) = self.stacking.iter().position(|&w| w == win) {
+            let win = self.stacking.remove(pos);
+            self.stacking.push(win);
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use coppe_common::{
+    event::Event,
+    window::{WorkspaceId, ALL_WORKSPACES},
+};
+use x11rb::errors::ConnectionError;
+use x11rb::protocol::xproto::*;
+use x11rb::wrapper::ConnectionExt as _;
+
+use super::client::{Client, WmState};
+use super::state::State;
+use crate::x11::X11Info;
+
+pub const DEFAULT_WORKSPACES: [&str; 4] = ["1", "2", "3", "4"];
+
+impl Client {
+    /// Check whether the client should be mapped while `current` workspace is shown.
+    pub fn is_visible(&self, current: WorkspaceId) -> bool {
+        self.state == WmState::Normal
+            && (self.workspace == current || self.workspace == ALL_WORKSPACES)
+    }
+}
+
+impl State {
+    pub fn is_valid_workspace(&self, workspace: WorkspaceId) -> bool {
+        workspace == ALL_WORKSPACES || (workspace as usize) < self.workspaces.len()
+    }
+
+    /// Show windows of the given workspace and hide all others. Returns the event that should be
+    /// dispatched to plugins, or `None` if nothing has changed.
+    pub fn switch_workspace(
+        &mut self,
+        x11: &X11Info,
+        workspace: WorkspaceId,
+    ) -> Result<Option<Event>, ConnectionError> {
+        let previous = self.current_workspace;
+        if workspace == previous || workspace as usize >= self.workspaces.len() {
+            return Ok(None);
+        }
+
+        self.current_workspace = workspace;
+        for client in self.clients.values_mut() {
+            update_visibility(x11, client, client.is_visible(previous), workspace)?;
+        }
+
+        if let Some(focused) = self.focused {
+            let visible = self
+                .clients
+                .get(&focused)
+                .is_some_and(|client| client.is_visible(workspace));
+            if !visible {
+                self.focused = None;
+                self.publish_active_window(x11)?;
+            }
+        }
+        self.publish_workspaces(x11)?;
+
+        Ok(Some(Event::WorkspaceChange {
+            previous,
+            current: workspace,
+        }))
+    }
+
+    /// Move window to another workspace, or to all workspaces with [ALL_WORKSPACES]. Returns
+    /// `false` if either window or workspace is unknown.
+    pub fn move_to_workspace(
+        &mut self,
+        x11: &X11Info,
+        win: Window,
+        workspace: WorkspaceId,
+    ) -> Result<bool, ConnectionError> {
+        if !self.is_valid_workspace(workspace) {
+            return Ok(false);
+        }
+        let current = self.current_workspace;
+        let client = match self.clients.get_mut(&win) {
+            Some(client) => client,
+            None => return Ok(false),
+        };
+
+        let was_visible = client.is_visible(current);
+        client.workspace = workspace;
+        update_visibility(x11, client, was_visible, current)?;
+        publish_window_workspace(x11, client)?;
+
+        Ok(true)
+    }
+
+    /// Replace the list of workspaces. Windows on removed workspaces are moved to the last one.
+    /// Returns the event that should be dispatched if current workspace was removed.
+    pub fn set_workspaces(
+        &mut self,
+        x11: &X11Info,
+        names: Vec<String>,
+    ) -> Result<Option<Event>, ConnectionError> {
+        if names.is_empty() {
+            return Ok(None);
+        }
+
+        let last = names.len() as WorkspaceId - 1;
+        self.workspaces = names;
+
+        let ids: Vec<Window> = self.clients.keys().copied().collect();
+        for win in ids {
+            let workspace = self.clients[&win].workspace;
+            if workspace != ALL_WORKSPACES && workspace > last {
+                self.move_to_workspace(x11, win, last)?;
+            }
+        }
+
+        if self.current_workspace > last {
+            let event = self.switch_workspace(x11, last)?;
+            Ok(event)
+        } else {
+            self.publish_workspaces(x11)?;
+            Ok(None)
+        }
+    }
+
+    /// Publish `_NET_NUMBER_OF_DESKTOPS`, `_NET_CURRENT_DESKTOP` and `_NET_DESKTOP_NAMES`.
+    /// Workspaces are shared by all screens, so the same values are set on every root window.
+    pub fn publish_workspaces(&self, x11: &X11Info) -> Result<(), ConnectionError> {
+        let mut names = Vec::new();
+        for name in &self.workspaces {
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+
+        for root in x11.roots() {
+            x11.conn.change_property32(
+                PropMode::REPLACE,
+                root,
+                x11.atoms._NET_NUMBER_OF_DESKTOPS,
+                AtomEnum::CARDINAL,
+                &[self.workspaces.len() as u32],
+            )?;
+            x11.conn.change_property32(
+                PropMode::REPLACE,
+                root,
+                x11.atoms._NET_CURRENT_DESKTOP,
+                AtomEnum::CARDINAL,
+                &[self.current_workspace],
+            )?;
+            x11.conn.change_property8(
+                PropMode::REPLACE,
+                root,
+                x11.atoms._NET_DESKTOP_NAMES,
+                x11.atoms.UTF8_STRING,
+                &names,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Publish `_NET_WM_DESKTOP` of the client.
+pub fn publish_window_workspace(x11: &X11Info, client: &Client) -> Result<(), ConnectionError> {
+    x11.conn.change_property32(
+        PropMode::REPLACE,
+        client.id,
+        x11.atoms._NET_WM_DESKTOP,
+        AtomEnum::CARDINAL,
+        &[client.workspace],
+    )?;
+    Ok(())
+}
+
+/// Map or unmap the client if its visibility has changed. Unmaps made here are not treated as
+/// client withdrawal.
+fn update_visibility(
+    x11: &X11Info,
+    client: &mut Client,
+    was_visible: bool,
+    current: WorkspaceId,
+) -> Result<(), ConnectionError> {
+    let visible = client.is_visible(current);
+    if was_visible && !visible {
+        client.ignore_unmap += 1;
+        x11.conn.unmap_window(client.id)?;
+    } else if !was_visible && visible {
+        x11.conn.map_window(client.id)?;
+    }
+    Ok(())
+}
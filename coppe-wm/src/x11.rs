@@ -1,6 +1,14 @@
+use coppe_common::window::{MoveResizeDirection, ScreenId};
 use std::sync::Arc;
 use x11rb::atom_manager;
-use x11rb::errors::{ConnectError, ConnectionError, ReplyError};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::cookie::VoidCookie;
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError, ReplyOrIdError};
+use x11rb::protocol::randr::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{
+    Atom, ClientMessageEvent, ConnectionExt as _, Cursor, EventMask, Screen, Timestamp, Window,
+    CLIENT_MESSAGE_EVENT,
+};
 use x11rb::rust_connection::RustConnection as X11Conn;
 
 atom_manager! {
@@ -8,6 +16,113 @@ atom_manager! {
         WM_PROTOCOLS,
         WM_TAKE_FOCUS,
         WM_DELETE_WINDOW,
+        WM_STATE,
+        WM_CHANGE_STATE,
+        UTF8_STRING,
+        _NET_SUPPORTED,
+        _NET_SUPPORTING_WM_CHECK,
+        _NET_CLIENT_LIST,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        WM_WINDOW_ROLE,
+        _NET_CLOSE_WINDOW,
+        _NET_MOVERESIZE_WINDOW,
+        _NET_WM_MOVERESIZE,
+        _NET_NUMBER_OF_DESKTOPS,
+        _NET_CURRENT_DESKTOP,
+        _NET_DESKTOP_NAMES,
+        _NET_WM_DESKTOP,
+        _NET_WM_STATE,
+        _NET_WM_STATE_MODAL,
+        _NET_WM_STATE_STICKY,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_STATE_SHADED,
+        _NET_WM_STATE_SKIP_TASKBAR,
+        _NET_WM_STATE_SKIP_PAGER,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_FULLSCREEN,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_STATE_BELOW,
+        _NET_WM_STATE_DEMANDS_ATTENTION,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_NORMAL,
+        _NET_WM_WINDOW_TYPE_DESKTOP,
+        _NET_WM_WINDOW_TYPE_DOCK,
+        _NET_WM_WINDOW_TYPE_TOOLBAR,
+        _NET_WM_WINDOW_TYPE_MENU,
+        _NET_WM_WINDOW_TYPE_UTILITY,
+        _NET_WM_WINDOW_TYPE_SPLASH,
+        _NET_WM_WINDOW_TYPE_DIALOG,
+        _NET_WM_WINDOW_TYPE_DROPDOWN_MENU,
+        _NET_WM_WINDOW_TYPE_POPUP_MENU,
+        _NET_WM_WINDOW_TYPE_TOOLTIP,
+        _NET_WM_WINDOW_TYPE_NOTIFICATION,
+        _NET_WM_WINDOW_TYPE_COMBO,
+        _NET_WM_WINDOW_TYPE_DND,
+    }
+}
+
+/// Cursors from the core cursor font, used during interactive move and resize.
+#[derive(Debug, Clone, Copy)]
+pub struct Cursors {
+    pub fleur: Cursor,
+    /// Resize cursors, indexed by [MoveResizeDirection] from `SizeTopLeft` to `SizeLeft`.
+    pub resize: [Cursor; 8],
+}
+
+impl Cursors {
+    /// Glyphs of `X11/cursorfont.h`: `XC_fleur` and resize cursors from `XC_top_left_corner`
+    /// clockwise to `XC_left_side`.
+    const FLEUR: u16 = 52;
+    const RESIZE: [u16; 8] = [134, 138, 136, 96, 14, 16, 12, 70];
+
+    fn new(conn: &X11Conn) -> Result<Self, ReplyOrIdError> {
+        let font = conn.generate_id()?;
+        conn.open_font(font, b"cursor")?;
+
+        let create = |glyph: u16| -> Result<Cursor, ReplyOrIdError> {
+            let cursor = conn.generate_id()?;
+            conn.create_glyph_cursor(
+                cursor,
+                font,
+                font,
+                glyph,
+                glyph + 1,
+                0,
+                0,
+                0,
+                u16::MAX,
+                u16::MAX,
+                u16::MAX,
+            )?;
+            Ok(cursor)
+        };
+
+        let mut resize = [x11rb::NONE; 8];
+        for (cursor, &glyph) in resize.iter_mut().zip(Self::RESIZE.iter()) {
+            *cursor = create(glyph)?;
+        }
+        let cursors = Self {
+            fleur: create(Self::FLEUR)?,
+            resize,
+        };
+
+        conn.close_font(font)?;
+        Ok(cursors)
+    }
+
+    /// Cursor for the drag direction, or `None` for keyboard-driven and cancel directions.
+    pub fn for_direction(&self, direction: MoveResizeDirection) -> Option<Cursor> {
+        match direction {
+            MoveResizeDirection::Move => Some(self.fleur),
+            MoveResizeDirection::SizeKeyboard
+            | MoveResizeDirection::MoveKeyboard
+            | MoveResizeDirection::Cancel => None,
+            resize => self.resize.get(resize as usize).copied(),
+        }
     }
 }
 
@@ -15,7 +130,10 @@ atom_manager! {
 pub struct X11Info {
     pub conn: Arc<X11Conn>,
     pub atoms: Atoms,
+    pub cursors: Cursors,
     pub screen_num: usize,
+    /// Version of the RandR extension, `None` if the server does not support it.
+    pub randr_version: Option<(u32, u32)>,
 }
 
 #[derive(Debug)]
@@ -23,17 +141,77 @@ pub enum Error {
     Connect(ConnectError),
     Connection(ConnectionError),
     Reply(ReplyError),
+    ReplyOrId(ReplyOrIdError),
 }
 
 impl X11Info {
     pub fn init() -> Result<Self, Error> {
         let (conn, screen_num) = X11Conn::connect(None).map_err(Error::Connect)?;
         let atoms = Atoms::new(&conn).map_err(Error::Connection)?;
+        let cursors = Cursors::new(&conn).map_err(Error::ReplyOrId)?;
+        let randr_version = randr_version(&conn).map_err(Error::Reply)?;
 
         Ok(Self {
             atoms: atoms.reply().map_err(Error::Reply)?,
+            cursors,
             conn: Arc::new(conn),
             screen_num,
+            randr_version,
         })
     }
+
+    /// Check whether the server supports at least the given RandR version.
+    pub fn has_randr(&self, major: u32, minor: u32) -> bool {
+        self.randr_version
+            .is_some_and(|version| version >= (major, minor))
+    }
+
+    pub fn screen(&self, screen: ScreenId) -> Option<&Screen> {
+        self.conn.setup().roots.get(screen as usize)
+    }
+
+    /// Root windows of all screens, in order of screen number.
+    pub fn roots(&self) -> impl Iterator<Item = Window> + '_ {
+        self.conn.setup().roots.iter().map(|screen| screen.root)
+    }
+
+    /// Find the screen number of a root window.
+    pub fn screen_of(&self, root: Window) -> Option<ScreenId> {
+        self.roots()
+            .position(|r| r == root)
+            .map(|screen| screen as ScreenId)
+    }
+
+    /// Send `WM_PROTOCOLS` client message, such as `WM_DELETE_WINDOW` or `WM_TAKE_FOCUS`.
+    pub fn send_protocol(
+        &self,
+        window: Window,
+        protocol: Atom,
+        time: Timestamp,
+    ) -> Result<VoidCookie<'_, X11Conn>, ConnectionError> {
+        let data = [protocol, time, 0, 0, 0];
+        let event = ClientMessageEvent {
+            response_type: CLIENT_MESSAGE_EVENT,
+            format: 32,
+            sequence: 0,
+            window,
+            type_: self.atoms.WM_PROTOCOLS,
+            data: data.into(),
+        };
+
+        self.conn
+            .send_event(false, window, EventMask::NO_EVENT, event)
+    }
+}
+
+/// Query the highest RandR version supported by both the server and the WM.
+fn randr_version(conn: &X11Conn) -> Result<Option<(u32, u32)>, ReplyError> {
+    if conn
+        .extension_information(randr::X11_EXTENSION_NAME)?
+        .is_none()
+    {
+        return Ok(None);
+    }
+    let version = conn.randr_query_version(1, 5)?.reply()?;
+    Ok(Some((version.major_version, version.minor_version)))
 }
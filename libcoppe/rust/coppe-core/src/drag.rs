@@ -0,0 +1,13 @@
+use crate::ffi;
+
+/// Enable or disable the built-in move with Mod4+left drag and resize with Mod4+right drag.
+/// Moves and resizes started by clients or with [window::begin_move](crate::window::begin_move)
+/// are not affected.
+pub fn set_enabled(enabled: bool) {
+    ffi::drag_set_enabled(enabled)
+}
+
+/// Set the distance in pixels at which moved windows snap to monitor edges. 0 disables snapping.
+pub fn set_snap_distance(distance: u16) {
+    ffi::drag_set_snap_distance(distance)
+}
@@ -1,20 +1,25 @@
+use crate::button::{ButtonIndex, Pointer};
 use crate::ffi;
-use crate::key::{Keycode, ModMask};
+use crate::key::{KeyInput, Keycode, Keysym, ModMask};
 use coppe_common::{
     encoding::{Decode, Encode, EncodeError},
     event::Event as CommonEvent,
 };
 
-pub use coppe_common::event::{id, SubscriptionEvent};
-pub use coppe_common::window::{Geometry, Window, WindowId};
+pub use coppe_common::event::{id, subscription_flag, SubscriptionEvent};
+pub use coppe_common::monitor::{Monitor, MonitorList};
+pub use coppe_common::window::{
+    ClientRequest, ClientRequestKind, Geometry, MoveResizeDirection, RequestSource, ScreenId,
+    StateAction, Window, WindowId, WindowState, WorkspaceId,
+};
 
 pub struct Subscription<'a> {
     buffer: &'a [u8],
 }
 
 impl<'a> Subscription<'a> {
-    pub fn subscribe(&self) {
-        ffi::subscribe(self.buffer)
+    pub fn subscribe(&self) -> Result<SubscribeStatus, SubscribeError> {
+        SubscribeError::from_code(ffi::subscribe(self.buffer))
     }
 
     pub fn unsubscribe(&self) {
@@ -26,24 +31,101 @@ impl<'a> Subscription<'a> {
     }
 }
 
+/// Successful result of subscribing to an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscribeStatus {
+    Subscribed,
+    /// Subscription was added, but another plugin is bound to the same key or button with
+    /// overlapping modifiers. Both plugins receive the event.
+    Conflict,
+}
+
+/// Error returned when subscribing to an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscribeError {
+    /// Subscription could not be decoded by the WM.
+    BadArgument,
+    /// Key or button is already grabbed by another X client. Subscription was not added.
+    AlreadyGrabbed,
+    /// Subscription was not added for an unknown reason.
+    Unknown,
+}
+
+impl SubscribeError {
+    pub fn from_code(code: i32) -> Result<SubscribeStatus, Self> {
+        match code {
+            0 => Ok(SubscribeStatus::Subscribed),
+            1 => Ok(SubscribeStatus::Conflict),
+            -3 => Err(Self::BadArgument),
+            -5 => Err(Self::AlreadyGrabbed),
+            _ => Err(Self::Unknown),
+        }
+    }
+}
+
 pub trait SubscriptionEventExt {
     fn init_without_filters(self, buffer: &mut [u8]) -> Result<Subscription, EncodeError>;
+    /// Conditional key binding without filters, see [replay].
+    fn init_conditional(self, buffer: &mut [u8]) -> Result<Subscription<'_>, EncodeError>;
 }
 
 impl SubscriptionEventExt for SubscriptionEvent {
     fn init_without_filters(self, buffer: &mut [u8]) -> Result<Subscription, EncodeError> {
         self.encode_to(buffer)?;
 
-        Ok(Subscription { buffer })
+        Ok(Subscription {
+            buffer: &buffer[..self.encoded_size()],
+        })
+    }
+
+    fn init_conditional(self, buffer: &mut [u8]) -> Result<Subscription<'_>, EncodeError> {
+        self.encode_to(buffer)?;
+        (self.id() | subscription_flag::CONDITIONAL).encode_to(&mut buffer[0..])?;
+
+        Ok(Subscription {
+            buffer: &buffer[..self.encoded_size()],
+        })
     }
 }
 
 pub enum Event {
     KeyPress(ModMask, Keycode),
     KeyRelease(ModMask, Keycode),
-    WindowAdd(WindowId),
-    WindowRemove(WindowId),
-    WindowConfigure(WindowId, Geometry),
+    WindowAdd(WindowId, ScreenId),
+    WindowRemove(WindowId, ScreenId),
+    WindowConfigure(WindowId, Geometry, ScreenId),
+    ClientRequest(ClientRequest),
+    WorkspaceChange {
+        previous: WorkspaceId,
+        current: WorkspaceId,
+    },
+    MonitorsChanged {
+        screen: ScreenId,
+        monitors: MonitorList,
+    },
+    WindowTitleChanged(WindowId),
+    WindowUrgencyChanged {
+        window: WindowId,
+        urgent: bool,
+    },
+    WindowPropertyChanged {
+        window: WindowId,
+        atom: u32,
+    },
+    PointerEnter(WindowId),
+    PointerLeave(WindowId),
+    WindowFocus {
+        previous: Option<WindowId>,
+        current: Option<WindowId>,
+    },
+    ButtonPress(ModMask, ButtonIndex, Pointer),
+    ButtonRelease(ModMask, ButtonIndex, Pointer),
+    PointerMotion(Pointer),
+    KeySymPress(ModMask, Keysym),
+    GrabbedKeyPress(KeyInput),
+    GrabbedKeyRelease(KeyInput),
+    KeyboardGrabEnd,
+    WindowClicked(ModMask, ButtonIndex, Pointer),
 }
 
 impl From<CommonEvent> for Event {
@@ -51,10 +133,43 @@ impl From<CommonEvent> for Event {
         match event {
             CommonEvent::KeyPress(key) => Event::KeyPress(key.modmask, key.keycode),
             CommonEvent::KeyRelease(key) => Event::KeyRelease(key.modmask, key.keycode),
-            CommonEvent::WindowAdd(window) => Event::WindowAdd(window),
-            CommonEvent::WindowRemove(window) => Event::WindowRemove(window),
+            CommonEvent::WindowAdd(window, screen) => Event::WindowAdd(window, screen),
+            CommonEvent::WindowRemove(window, screen) => Event::WindowRemove(window, screen),
             CommonEvent::WindowConfigure(window) => {
-                Event::WindowConfigure(window.id, window.geometry)
+                Event::WindowConfigure(window.id, window.geometry, window.screen)
+            }
+            CommonEvent::ClientRequest(request) => Event::ClientRequest(request),
+            CommonEvent::WorkspaceChange { previous, current } => {
+                Event::WorkspaceChange { previous, current }
+            }
+            CommonEvent::MonitorsChanged { screen, monitors } => {
+                Event::MonitorsChanged { screen, monitors }
+            }
+            CommonEvent::WindowTitleChanged(window) => Event::WindowTitleChanged(window),
+            CommonEvent::WindowUrgencyChanged { window, urgent } => {
+                Event::WindowUrgencyChanged { window, urgent }
+            }
+            CommonEvent::WindowPropertyChanged { window, atom } => {
+                Event::WindowPropertyChanged { window, atom }
+            }
+            CommonEvent::PointerEnter(window) => Event::PointerEnter(window),
+            CommonEvent::PointerLeave(window) => Event::PointerLeave(window),
+            CommonEvent::WindowFocus { previous, current } => {
+                Event::WindowFocus { previous, current }
+            }
+            CommonEvent::ButtonPress(button, pointer) => {
+                Event::ButtonPress(button.modmask, button.index, pointer)
+            }
+            CommonEvent::ButtonRelease(button, pointer) => {
+                Event::ButtonRelease(button.modmask, button.index, pointer)
+            }
+            CommonEvent::PointerMotion(pointer) => Event::PointerMotion(pointer),
+            CommonEvent::KeySymPress(modmask, keysym) => Event::KeySymPress(modmask, keysym),
+            CommonEvent::GrabbedKeyPress(input) => Event::GrabbedKeyPress(input),
+            CommonEvent::GrabbedKeyRelease(input) => Event::GrabbedKeyRelease(input),
+            CommonEvent::KeyboardGrabEnd => Event::KeyboardGrabEnd,
+            CommonEvent::WindowClicked(button, pointer) => {
+                Event::WindowClicked(button.modmask, button.index, pointer)
             }
         }
     }
@@ -69,7 +184,7 @@ impl Decode for Event {
 }
 
 pub fn read_parse() -> Option<Event> {
-    let mut buffer = [0; 16];
+    let mut buffer = [0; 256];
     read(&mut buffer);
     CommonEvent::decode(&buffer).map(Into::into).ok()
 }
@@ -85,3 +200,10 @@ pub fn read_from(buffer: &mut [u8], offset: usize) -> isize {
 pub fn len() -> usize {
     ffi::event_len()
 }
+
+/// Pass the key press that is being handled on to the focused client. Has effect only for
+/// conditional key bindings, the key press is replayed if none of the subscribed plugins consume
+/// it.
+pub fn replay() {
+    ffi::event_replay()
+}
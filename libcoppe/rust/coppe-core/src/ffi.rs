@@ -1,4 +1,4 @@
-use coppe_common::window::WindowId;
+use coppe_common::window::{FocusPolicy, MoveResizeDirection, ScreenId, WindowId, WorkspaceId};
 
 pub(crate) mod raw {
     extern "C" {
@@ -7,12 +7,24 @@ pub(crate) mod raw {
         pub fn unsubscribe(event_ptr: *const u8, event_len: usize) -> i32;
         pub fn event_read(buf_ptr: *mut u8, buf_len: usize, offset: usize) -> isize;
         pub fn event_len() -> usize;
+        pub fn event_replay();
 
         // Window management
         pub fn window_move(id: u32, x: i16, y: i16) -> i32;
         pub fn window_resize(id: u32, width: u16, height: u16) -> i32;
         pub fn window_move_resize(id: u32, x: i16, y: i16, width: u16, height: u16) -> i32;
+        pub fn window_resize_with_hints(id: u32, width: u16, height: u16) -> i32;
+        pub fn window_move_resize_with_hints(
+            id: u32,
+            x: i16,
+            y: i16,
+            width: u16,
+            height: u16,
+        ) -> i32;
         pub fn window_focus(id: u32) -> i32;
+        pub fn window_raise(id: u32) -> i32;
+        pub fn window_begin_move(id: u32) -> i32;
+        pub fn window_begin_resize(id: u32, direction: u32) -> i32;
         pub fn window_get_properties(
             id: u32,
             x: *mut i16,
@@ -20,7 +32,36 @@ pub(crate) mod raw {
             width: *mut u16,
             height: *mut u16,
         ) -> i32;
+        pub fn window_get_size_hints(id: u32, buf_ptr: *mut u8, buf_len: usize) -> i32;
+        pub fn window_get_title(id: u32, buf_ptr: *mut u8, buf_len: usize) -> i32;
+        pub fn window_get_class(id: u32, buf_ptr: *mut u8, buf_len: usize) -> i32;
+        pub fn window_get_role(id: u32, buf_ptr: *mut u8, buf_len: usize) -> i32;
+        pub fn window_get_pid(id: u32) -> i32;
+        pub fn window_get_type(id: u32) -> i32;
+        pub fn window_list(buf_ptr: *mut u8, buf_len: usize) -> i32;
+        pub fn window_focused() -> u32;
         pub fn window_close(id: u32) -> i32;
+        pub fn window_move_to_workspace(id: u32, workspace: u32) -> i32;
+
+        // Workspaces
+        pub fn workspace_switch(workspace: u32) -> i32;
+        pub fn workspace_current() -> u32;
+        pub fn workspace_set_names(names_ptr: *const u8, names_len: usize) -> i32;
+
+        // Focus
+        pub fn focus_set_policy(policy: u32) -> i32;
+        pub fn focus_set_click_enabled(enabled: u32);
+
+        // Interactive move and resize
+        pub fn drag_set_enabled(enabled: u32) -> i32;
+        pub fn drag_set_snap_distance(distance: u32) -> i32;
+
+        // Keyboard grab
+        pub fn keyboard_grab(timeout_ms: u32) -> i32;
+        pub fn keyboard_ungrab() -> i32;
+
+        // Monitors
+        pub fn monitors_list(screen: u32, buf_ptr: *mut u8, buf_len: usize) -> i32;
 
         // Commands
         pub fn spawn(cmd_ptr: *const u8, cmd_len: usize) -> i32;
@@ -30,10 +71,8 @@ pub(crate) mod raw {
     }
 }
 
-pub fn subscribe(event: &[u8]) {
-    unsafe {
-        raw::subscribe(event.as_ptr(), event.len());
-    }
+pub fn subscribe(event: &[u8]) -> i32 {
+    unsafe { raw::subscribe(event.as_ptr(), event.len()) }
 }
 
 pub fn unsubscribe(event: &[u8]) {
@@ -50,6 +89,10 @@ pub fn event_len() -> usize {
     unsafe { raw::event_len() }
 }
 
+pub fn event_replay() {
+    unsafe { raw::event_replay() }
+}
+
 pub fn spawn(command: &str) -> i32 {
     unsafe { raw::spawn(command.as_ptr() as *const u8, command.len()) }
 }
@@ -72,12 +115,38 @@ pub fn window_move_resize(id: WindowId, x: i16, y: i16, width: u16, height: u16)
     }
 }
 
+pub fn window_resize_with_hints(id: WindowId, width: u16, height: u16) {
+    unsafe {
+        raw::window_resize_with_hints(id, width, height);
+    }
+}
+
+pub fn window_move_resize_with_hints(id: WindowId, x: i16, y: i16, width: u16, height: u16) {
+    unsafe {
+        raw::window_move_resize_with_hints(id, x, y, width, height);
+    }
+}
+
 pub fn window_focus(id: WindowId) {
     unsafe {
         raw::window_focus(id);
     }
 }
 
+pub fn window_raise(id: WindowId) {
+    unsafe {
+        raw::window_raise(id);
+    }
+}
+
+pub fn window_begin_move(id: WindowId) -> i32 {
+    unsafe { raw::window_begin_move(id) }
+}
+
+pub fn window_begin_resize(id: WindowId, direction: MoveResizeDirection) -> i32 {
+    unsafe { raw::window_begin_resize(id, direction as u32) }
+}
+
 pub fn window_get_properties(
     id: WindowId,
     x: &mut i16,
@@ -96,12 +165,102 @@ pub fn window_get_properties(
     }
 }
 
+pub fn window_get_size_hints(id: WindowId, buffer: &mut [u8]) -> i32 {
+    unsafe { raw::window_get_size_hints(id, buffer.as_mut_ptr(), buffer.len()) }
+}
+
+pub fn window_get_title(id: WindowId, buffer: &mut [u8]) -> i32 {
+    unsafe { raw::window_get_title(id, buffer.as_mut_ptr(), buffer.len()) }
+}
+
+pub fn window_get_class(id: WindowId, buffer: &mut [u8]) -> i32 {
+    unsafe { raw::window_get_class(id, buffer.as_mut_ptr(), buffer.len()) }
+}
+
+pub fn window_get_role(id: WindowId, buffer: &mut [u8]) -> i32 {
+    unsafe { raw::window_get_role(id, buffer.as_mut_ptr(), buffer.len()) }
+}
+
+pub fn window_get_pid(id: WindowId) -> i32 {
+    unsafe { raw::window_get_pid(id) }
+}
+
+pub fn window_get_type(id: WindowId) -> i32 {
+    unsafe { raw::window_get_type(id) }
+}
+
+pub fn window_list(buffer: &mut [u8]) -> i32 {
+    unsafe { raw::window_list(buffer.as_mut_ptr(), buffer.len()) }
+}
+
+pub fn window_focused() -> WindowId {
+    unsafe { raw::window_focused() }
+}
+
 pub fn window_close(id: WindowId) {
     unsafe {
         raw::window_close(id);
     }
 }
 
+pub fn window_move_to_workspace(id: WindowId, workspace: WorkspaceId) {
+    unsafe {
+        raw::window_move_to_workspace(id, workspace);
+    }
+}
+
+pub fn workspace_switch(workspace: WorkspaceId) {
+    unsafe {
+        raw::workspace_switch(workspace);
+    }
+}
+
+pub fn workspace_current() -> WorkspaceId {
+    unsafe { raw::workspace_current() }
+}
+
+pub fn workspace_set_names(names: &str) {
+    unsafe {
+        raw::workspace_set_names(names.as_ptr(), names.len());
+    }
+}
+
+pub fn focus_set_policy(policy: FocusPolicy) {
+    unsafe {
+        raw::focus_set_policy(policy as u32);
+    }
+}
+
+pub fn focus_set_click_enabled(enabled: bool) {
+    unsafe {
+        raw::focus_set_click_enabled(enabled as u32);
+    }
+}
+
+pub fn drag_set_enabled(enabled: bool) {
+    unsafe {
+        raw::drag_set_enabled(enabled as u32);
+    }
+}
+
+pub fn drag_set_snap_distance(distance: u16) {
+    unsafe {
+        raw::drag_set_snap_distance(u32::from(distance));
+    }
+}
+
+pub fn keyboard_grab(timeout_ms: u32) -> i32 {
+    unsafe { raw::keyboard_grab(timeout_ms) }
+}
+
+pub fn keyboard_ungrab() -> i32 {
+    unsafe { raw::keyboard_ungrab() }
+}
+
+pub fn monitors_list(screen: ScreenId, buffer: &mut [u8]) -> i32 {
+    unsafe { raw::monitors_list(screen, buffer.as_mut_ptr(), buffer.len()) }
+}
+
 pub fn debug_log(message: &str) {
     unsafe {
         raw::debug_log(message.as_ptr() as *const u8, message.len());
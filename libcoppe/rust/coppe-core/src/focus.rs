@@ -0,0 +1,14 @@
+pub use coppe_common::window::FocusPolicy;
+
+use crate::ffi;
+
+/// Change the built-in focus policy. Use [FocusPolicy::None] to manage focus from the plugin.
+pub fn set_policy(policy: FocusPolicy) {
+    ffi::focus_set_policy(policy)
+}
+
+/// Enable or disable focusing and raising clicked windows with [FocusPolicy::Click]. Clicks on
+/// unfocused windows are still reported with `WindowClicked`, so that the plugin can handle them.
+pub fn set_click_enabled(enabled: bool) {
+    ffi::focus_set_click_enabled(enabled)
+}
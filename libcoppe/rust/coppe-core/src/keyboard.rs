@@ -0,0 +1,18 @@
+use crate::ffi;
+
+/// Grab the keyboard, so that every key press and release is delivered to the plugin as
+/// [GrabbedKeyPress](crate::event::Event::GrabbedKeyPress) and
+/// [GrabbedKeyRelease](crate::event::Event::GrabbedKeyRelease) until [ungrab] is called.
+///
+/// The WM releases the grab and sends [KeyboardGrabEnd](crate::event::Event::KeyboardGrabEnd)
+/// when Escape is pressed or no key is pressed for `timeout_ms` milliseconds. 0 uses the default
+/// timeout of 10 seconds, timeouts longer than a minute are capped. Returns `false` if the
+/// keyboard is grabbed by another plugin or X client.
+pub fn grab(timeout_ms: u32) -> bool {
+    ffi::keyboard_grab(timeout_ms) == 0
+}
+
+/// Release the keyboard grab. Returns `false` if the plugin does not hold the grab.
+pub fn ungrab() -> bool {
+    ffi::keyboard_ungrab() == 0
+}
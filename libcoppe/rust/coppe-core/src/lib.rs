@@ -2,10 +2,19 @@
 
 pub mod command;
 pub mod debug;
+pub mod drag;
 pub mod event;
 pub mod ffi;
+pub mod focus;
+pub mod keyboard;
+pub mod monitor;
 pub mod prelude;
 pub mod window;
+pub mod workspace;
+
+pub mod button {
+    pub use coppe_common::button::*;
+}
 
 pub mod key {
     pub use coppe_common::key::*;
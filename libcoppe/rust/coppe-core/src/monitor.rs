@@ -0,0 +1,14 @@
+pub use coppe_common::monitor::{Monitor, MonitorList, MAX_MONITORS};
+pub use coppe_common::window::ScreenId;
+
+use crate::ffi;
+use coppe_common::encoding::Decode;
+
+/// Query current monitor layout of the screen. Returns an empty list if the screen does not exist.
+pub fn list(screen: ScreenId) -> MonitorList {
+    let mut buffer = [0; MonitorList::MAX_ENCODED_SIZE];
+    if ffi::monitors_list(screen, &mut buffer) < 0 {
+        return MonitorList::new();
+    }
+    MonitorList::decode(&buffer).unwrap_or_default()
+}
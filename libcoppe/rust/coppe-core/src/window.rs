@@ -1,6 +1,7 @@
 pub use coppe_common::window::*;
 
 use crate::ffi;
+use coppe_common::encoding::Decode;
 
 pub fn move_to(id: WindowId, x: i16, y: i16) {
     ffi::window_move(id, x, y)
@@ -10,10 +11,33 @@ pub fn resize(id: WindowId, width: u16, height: u16) {
     ffi::window_resize(id, width, height)
 }
 
+/// Resize window, adjusting the size to the client's size hints.
+pub fn resize_with_hints(id: WindowId, width: u16, height: u16) {
+    ffi::window_resize_with_hints(id, width, height)
+}
+
+/// Give input focus to the window. Stacking order is not changed, see [raise].
 pub fn focus(id: WindowId) {
     ffi::window_focus(id)
 }
 
+/// Place the window on top of the stack.
+pub fn raise(id: WindowId) {
+    ffi::window_raise(id)
+}
+
+/// Start interactive move of the window. The window follows the pointer until a mouse button is
+/// released. Returns `false` if the pointer could not be grabbed.
+pub fn begin_move(id: WindowId) -> bool {
+    ffi::window_begin_move(id) == 0
+}
+
+/// Start interactive resize of the window from the edge or corner given by one of the `Size*`
+/// directions. Returns `false` if the direction is invalid or the pointer could not be grabbed.
+pub fn begin_resize(id: WindowId, direction: MoveResizeDirection) -> bool {
+    ffi::window_begin_resize(id, direction) == 0
+}
+
 pub fn get_geometry(id: WindowId) -> Geometry {
     let mut geometry = Geometry {
         x: 0,
@@ -37,6 +61,108 @@ pub fn set_geometry(id: WindowId, geometry: Geometry) {
     ffi::window_move_resize(id, geometry.x, geometry.y, geometry.width, geometry.height)
 }
 
+/// Move and resize window, adjusting the size to the client's size hints.
+pub fn set_geometry_with_hints(id: WindowId, geometry: Geometry) {
+    ffi::window_move_resize_with_hints(id, geometry.x, geometry.y, geometry.width, geometry.height)
+}
+
+/// Query `WM_NORMAL_HINTS` of a managed window.
+pub fn get_size_hints(id: WindowId) -> Option<SizeHints> {
+    let mut buffer = [0; 64];
+    if ffi::window_get_size_hints(id, &mut buffer) < 0 {
+        return None;
+    }
+    SizeHints::decode(&buffer).ok()
+}
+
 pub fn close(id: WindowId) {
     ffi::window_close(id)
 }
+
+/// Move window to another workspace. Use [ALL_WORKSPACES] to show window on every workspace.
+pub fn move_to_workspace(id: WindowId, workspace: WorkspaceId) {
+    ffi::window_move_to_workspace(id, workspace)
+}
+
+/// Read window title to the buffer. The title is truncated if the buffer is too small. Returns
+/// `None` if the window is unknown or has no title.
+pub fn get_title(id: WindowId, buffer: &mut [u8]) -> Option<&str> {
+    let len = ffi::window_get_title(id, buffer);
+    read_str(buffer, len)
+}
+
+/// Read `WM_CLASS` to the buffer. Returns `(instance, class)` pair or `None` if the window is
+/// unknown, has no class or the buffer is too small.
+pub fn get_class(id: WindowId, buffer: &mut [u8]) -> Option<(&str, &str)> {
+    let len = ffi::window_get_class(id, buffer);
+    if len < 0 || len as usize > buffer.len() {
+        return None;
+    }
+    let value = core::str::from_utf8(&buffer[..len as usize]).ok()?;
+    let mut parts = value.splitn(2, '\0');
+    Some((parts.next()?, parts.next()?))
+}
+
+/// Read `WM_WINDOW_ROLE` to the buffer. The role is truncated if the buffer is too small. Returns
+/// `None` if the window is unknown or has no role.
+pub fn get_role(id: WindowId, buffer: &mut [u8]) -> Option<&str> {
+    let len = ffi::window_get_role(id, buffer);
+    read_str(buffer, len)
+}
+
+/// Query `_NET_WM_PID` of the window.
+pub fn get_pid(id: WindowId) -> Option<u32> {
+    let pid = ffi::window_get_pid(id);
+    (pid > 0).then_some(pid as u32)
+}
+
+/// Query EWMH window type.
+pub fn get_type(id: WindowId) -> Option<WindowType> {
+    let window_type = ffi::window_get_type(id);
+    if window_type < 0 {
+        return None;
+    }
+    WindowType::from_u8(window_type as u8)
+}
+
+/// Size of a single [list] entry in bytes.
+pub const LIST_ENTRY_SIZE: usize = 17;
+
+/// Read managed windows in order of mapping to the buffer. Only windows that fit in the buffer
+/// are returned, see [list_size].
+pub fn list(buffer: &mut [u8]) -> impl Iterator<Item = (Window, MapState)> + '_ {
+    let len = ffi::window_list(buffer).max(0) as usize;
+    let len = core::cmp::min(len, buffer.len());
+    buffer[..len]
+        .chunks_exact(LIST_ENTRY_SIZE)
+        .filter_map(|entry| {
+            let window = Window::decode(entry).ok()?;
+            let map_state = MapState::from_u8(entry[LIST_ENTRY_SIZE - 1])?;
+            Some((window, map_state))
+        })
+}
+
+/// Size of buffer required to read all managed windows with [list].
+pub fn list_size() -> usize {
+    ffi::window_list(&mut []).max(0) as usize
+}
+
+/// Query focused window.
+pub fn focused() -> Option<WindowId> {
+    match ffi::window_focused() {
+        0 => None,
+        id => Some(id),
+    }
+}
+
+fn read_str(buffer: &[u8], len: i32) -> Option<&str> {
+    if len <= 0 {
+        return None;
+    }
+    let bytes = &buffer[..core::cmp::min(len as usize, buffer.len())];
+    match core::str::from_utf8(bytes) {
+        Ok(value) => Some(value),
+        // Truncation may split a multibyte character
+        Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).ok(),
+    }
+}
@@ -0,0 +1,16 @@
+pub use coppe_common::window::{WorkspaceId, ALL_WORKSPACES};
+
+use crate::ffi;
+
+pub fn switch(workspace: WorkspaceId) {
+    ffi::workspace_switch(workspace)
+}
+
+pub fn current() -> WorkspaceId {
+    ffi::workspace_current()
+}
+
+/// Replace the list of workspaces. Expects NUL-separated workspace names, e.g. `"web\0code\0chat"`.
+pub fn set_names(names: &str) {
+    ffi::workspace_set_names(names)
+}
@@ -1,25 +1,26 @@
 use coppe_common::encoding::{Decode, EncodeExt};
 use coppe_core::ffi;
 
-pub use coppe_common::event::Subscription;
+pub use coppe_common::event::{Subscription, SubscriptionFilter};
 pub use coppe_core::event::{
-    id, len, read as read_to, Event, Subscription as SubscriptionBuffer, SubscriptionEvent,
+    id, len, read as read_to, replay, Event, SubscribeError, SubscribeStatus,
+    Subscription as SubscriptionBuffer, SubscriptionEvent,
 };
 
 pub trait SubscriptionExt {
-    fn subscribe(&self);
+    fn subscribe(&self) -> Result<SubscribeStatus, SubscribeError>;
     fn unsubscribe(&self);
 }
 
 impl SubscriptionExt for Subscription {
-    fn subscribe(&self) {
-        let buffer = self.event.encode_to_vec().unwrap();
+    fn subscribe(&self) -> Result<SubscribeStatus, SubscribeError> {
+        let buffer = self.encode_to_vec().unwrap();
 
-        ffi::subscribe(buffer.as_slice())
+        SubscribeError::from_code(ffi::subscribe(buffer.as_slice()))
     }
 
     fn unsubscribe(&self) {
-        let buffer = self.event.encode_to_vec().unwrap();
+        let buffer = self.encode_to_vec().unwrap();
         ffi::unsubscribe(buffer.as_slice())
     }
 }
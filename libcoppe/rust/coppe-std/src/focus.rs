@@ -0,0 +1 @@
+pub use coppe_core::focus::*;
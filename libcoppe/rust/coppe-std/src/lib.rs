@@ -1,12 +1,18 @@
 pub mod command;
 pub mod debug;
+pub mod drag;
 pub mod event;
+pub mod focus;
+pub mod keyboard;
+pub mod monitor;
 pub mod prelude;
+pub mod window;
+pub mod workspace;
 
-pub mod key {
-    pub use coppe_core::key::*;
+pub mod button {
+    pub use coppe_core::button::*;
 }
 
-pub mod window {
-    pub use coppe_core::window::*;
+pub mod key {
+    pub use coppe_core::key::*;
 }
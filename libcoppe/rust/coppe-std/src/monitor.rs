@@ -0,0 +1,6 @@
+pub use coppe_core::monitor::{Monitor, MonitorList, ScreenId, MAX_MONITORS};
+
+/// Query current monitor layout of the screen. Returns an empty list if the screen does not exist.
+pub fn list(screen: ScreenId) -> Vec<Monitor> {
+    coppe_core::monitor::list(screen).as_slice().to_vec()
+}
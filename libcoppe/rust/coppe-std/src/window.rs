@@ -0,0 +1,59 @@
+pub use coppe_core::window::*;
+
+use coppe_core::ffi;
+
+/// Query window title.
+pub fn title(id: WindowId) -> Option<String> {
+    read_string(|buffer| ffi::window_get_title(id, buffer))
+}
+
+/// Query `WM_CLASS` as `(instance, class)` pair.
+pub fn class(id: WindowId) -> Option<(String, String)> {
+    let value = read_string(|buffer| ffi::window_get_class(id, buffer))?;
+    let mut parts = value.splitn(2, '\0');
+    Some((parts.next()?.to_owned(), parts.next()?.to_owned()))
+}
+
+/// Query `WM_WINDOW_ROLE`.
+pub fn role(id: WindowId) -> Option<String> {
+    read_string(|buffer| ffi::window_get_role(id, buffer))
+}
+
+/// Query `_NET_WM_PID`.
+pub fn pid(id: WindowId) -> Option<u32> {
+    get_pid(id)
+}
+
+/// Query EWMH window type.
+pub fn window_type(id: WindowId) -> Option<WindowType> {
+    get_type(id)
+}
+
+/// Query all managed windows in order of mapping.
+pub fn list() -> Vec<Window> {
+    list_with_state()
+        .into_iter()
+        .map(|(window, _)| window)
+        .collect()
+}
+
+/// Query all managed windows in order of mapping, along with their mapping state.
+pub fn list_with_state() -> Vec<(Window, MapState)> {
+    let mut buffer = vec![0; list_size()];
+    coppe_core::window::list(&mut buffer).collect()
+}
+
+fn read_string<F: Fn(&mut [u8]) -> i32>(read: F) -> Option<String> {
+    let mut buffer = vec![0; 256];
+    let mut len = read(&mut buffer);
+    if len > 0 && len as usize > buffer.len() {
+        buffer.resize(len as usize, 0);
+        len = read(&mut buffer);
+    }
+    if len <= 0 {
+        return None;
+    }
+
+    buffer.truncate(len as usize);
+    String::from_utf8(buffer).ok()
+}
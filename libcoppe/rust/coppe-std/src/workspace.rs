@@ -0,0 +1,8 @@
+pub use coppe_core::workspace::{current, switch, WorkspaceId, ALL_WORKSPACES};
+
+use coppe_core::ffi;
+
+pub fn set_names<N: AsRef<str>>(names: &[N]) {
+    let names: Vec<&str> = names.iter().map(AsRef::as_ref).collect();
+    ffi::workspace_set_names(&names.join("\0"))
+}
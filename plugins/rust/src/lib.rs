@@ -1,25 +1,43 @@
 use coppe_std::command;
-use coppe_std::event::{self, Event, SubscriptionEvent};
+use coppe_std::debug::log;
+use coppe_std::event::{self, Event, SubscribeStatus, SubscriptionEvent};
 use coppe_std::key::{Key, Keycode, ModMask};
 use coppe_std::prelude::*;
 
 #[no_mangle]
 pub extern "C" fn init() {
-    let mut sub_buffer = [0; 7];
-
-    SubscriptionEvent::KeyPress(Key::new(ModMask::M4, Keycode::Return))
-        .init_without_filters(&mut sub_buffer)
-        .unwrap()
-        .subscribe();
+    subscribe(SubscriptionEvent::KeyPress(Key::new(
+        ModMask::M4,
+        Keycode::Return,
+    )));
 
-    SubscriptionEvent::KeyPress(Key::new(ModMask::M4, Keycode::X))
-        .init_without_filters(&mut sub_buffer)
-        .unwrap()
-        .subscribe();
+    subscribe(SubscriptionEvent::KeyPress(Key::new(
+        ModMask::M4,
+        Keycode::X,
+    )));
 
     command::spawn("feh --bg-scale /home/anfid/Pictures/Wallpapers/Sth2.png");
 }
 
+/// Subscribe to the event, logging failures instead of aborting initialization.
+fn subscribe(event: SubscriptionEvent) {
+    let mut sub_buffer = [0; 7];
+
+    match event.clone().init_without_filters(&mut sub_buffer) {
+        Ok(subscription) => match subscription.subscribe() {
+            Ok(SubscribeStatus::Subscribed) => {}
+            Ok(SubscribeStatus::Conflict) => {
+                log(format!("{:?} is also bound by another plugin", event))
+            }
+            Err(e) => log(format!("Unable to subscribe to {:?}: {:?}", event, e)),
+        },
+        Err(e) => log(format!(
+            "Unable to encode subscription {:?}: {:?}",
+            event, e
+        )),
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn handle() {
     let terminal = "kitty";
@@ -1,5 +1,5 @@
 use coppe_std::debug::log;
-use coppe_std::event::{self, Event, SubscriptionEvent};
+use coppe_std::event::{self, Event, SubscribeStatus, SubscriptionEvent};
 use coppe_std::key::{Key, Keycode, ModMask};
 use coppe_std::prelude::*;
 use coppe_std::window::{Geometry, WindowId};
@@ -13,32 +13,38 @@ lazy_static! {
 
 #[no_mangle]
 pub extern "C" fn init() {
-    let mut sub_buffer = [0; 7];
-
-    SubscriptionEvent::KeyPress(Key::new(ModMask::M4, Keycode::Z))
-        .init_without_filters(&mut sub_buffer)
-        .unwrap()
-        .subscribe();
+    subscribe(SubscriptionEvent::KeyPress(Key::new(
+        ModMask::M4,
+        Keycode::Z,
+    )));
 
-    SubscriptionEvent::KeyRelease(Key::new(ModMask::M4, Keycode::Z))
-        .init_without_filters(&mut sub_buffer)
-        .unwrap()
-        .subscribe();
+    subscribe(SubscriptionEvent::KeyRelease(Key::new(
+        ModMask::M4,
+        Keycode::Z,
+    )));
 
-    SubscriptionEvent::WindowAdd
-        .init_without_filters(&mut sub_buffer)
-        .unwrap()
-        .subscribe();
+    subscribe(SubscriptionEvent::WindowAdd);
+    subscribe(SubscriptionEvent::WindowRemove);
+    subscribe(SubscriptionEvent::WindowConfigure);
+}
 
-    SubscriptionEvent::WindowRemove
-        .init_without_filters(&mut sub_buffer)
-        .unwrap()
-        .subscribe();
+/// Subscribe to the event, logging failures instead of aborting initialization.
+fn subscribe(event: SubscriptionEvent) {
+    let mut sub_buffer = [0; 7];
 
-    SubscriptionEvent::WindowConfigure
-        .init_without_filters(&mut sub_buffer)
-        .unwrap()
-        .subscribe();
+    match event.clone().init_without_filters(&mut sub_buffer) {
+        Ok(subscription) => match subscription.subscribe() {
+            Ok(SubscribeStatus::Subscribed) => {}
+            Ok(SubscribeStatus::Conflict) => {
+                log(format!("{:?} is also bound by another plugin", event))
+            }
+            Err(e) => log(format!("Unable to subscribe to {:?}: {:?}", event, e)),
+        },
+        Err(e) => log(format!(
+            "Unable to encode subscription {:?}: {:?}",
+            event, e
+        )),
+    }
 }
 
 #[no_mangle]
@@ -52,15 +58,15 @@ pub extern "C" fn handle() {
             Event::KeyRelease(ModMask::M4, Keycode::Z) => {
                 log("Win+Z released");
             }
-            Event::WindowAdd(id) => {
-                log(format!("New window: {}", id));
+            Event::WindowAdd(id, screen) => {
+                log(format!("New window: {} on screen {}", id, screen));
                 WINDOWS.lock().unwrap().insert(id, None);
             }
-            Event::WindowRemove(id) => {
+            Event::WindowRemove(id, _) => {
                 log(format!("Window removed: {}", id));
                 WINDOWS.lock().unwrap().remove(&id);
             }
-            Event::WindowConfigure(id, geometry) => {
+            Event::WindowConfigure(id, geometry, _) => {
                 log(format!("Window updated: {}, {:?}", id, geometry));
                 WINDOWS.lock().unwrap().insert(id, Some(geometry));
             }